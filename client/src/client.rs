@@ -1,18 +1,24 @@
-use merkle_tree::{hash, interface::{FetchRequest, FetchResponse, StoreRequest, StoreResponse}, verify};
+use merkle_tree::{interface::{AppendRequest, AppendResponse, FetchRangeRequest, FetchRangeResponse, FetchRequest, FetchResponse, StoreRequest, StoreResponse, UpdateRequest, UpdateResponse}, chunk::DEFAULT_CHUNK_SIZE, file_leaf_from_reader, salt_leaf, tree_head::VerifyingKey, verify_consistency, verify_update, Digest, MerkleHasher, MerkleRootBuilder, Salt, Scheme, Sha256, SignedTreeHead, SALT_LEN};
 use simple_database::SimpleStringDb;
 use std::fs;
 
 static FILES_DIR_NAME: &str = "files";
 static ROOT_STORAGE_FILE_NAME: &str = "root.db";
+// Public key of the server, pinned by copying server_key.pub from the server's directory
+static SERVER_KEY_FILE_NAME: &str = "server_key.pub";
+// Scheme used for new stores. Records in root.db keep the scheme they were stored with
+static STORE_SCHEME: Scheme = Scheme::LATEST;
+// Chunk size for new stores, so ranges of the files can be fetched
+static STORE_CHUNK_SIZE: Option<usize> = Some(DEFAULT_CHUNK_SIZE);
 
 pub struct Client {
     server_end_point: String,
 }
 
 impl Client {
-    pub fn new(server_end_point: &String) -> Self {
+    pub fn new(server_end_point: &str) -> Self {
         Client {
-            server_end_point: server_end_point.clone(),
+            server_end_point: server_end_point.to_owned(),
         }
     }
 
@@ -23,7 +29,7 @@ impl Client {
     {
         let client = reqwest::blocking::Client::new();
         let builder = client.get(&format!("{}/{}", self.server_end_point, path));
-        let value = builder.json(body).send().unwrap().text().unwrap();
+        let value = read_response(builder.json(body).send().unwrap());
         serde_json::from_str(value.as_str()).unwrap()
     }
 
@@ -35,8 +41,8 @@ impl Client {
         let client = reqwest::blocking::Client::new();
         let builder = client.post(&format!("{}/{}", self.server_end_point, path));
 
-        let value = builder.json(body).send().unwrap().text().unwrap();
-        serde_json::from_str(value.as_str()).expect(&format!("failed to parse: {}", value.as_str()))
+        let value = read_response(builder.json(body).send().unwrap());
+        serde_json::from_str(value.as_str()).unwrap_or_else(|_| panic!("failed to parse: {}", value.as_str()))
     }
     
     fn read_files(&self) -> Vec<String> {
@@ -48,7 +54,7 @@ impl Client {
       let paths_count = paths.count();
       let mut inputs: Vec<String> = Vec::with_capacity(paths_count);
       for index in 0..paths_count {
-        let path_string: String = String::from(format!("./{}/file", FILES_DIR_NAME)) + &index.to_string();
+        let path_string: String = format!("./{}/file", FILES_DIR_NAME) + &index.to_string();
         let file_contents = match fs::read(&path_string) {
         Ok(content) => content,
           Err(_) => panic!("File {} expected but does not exist. Ensure all files follow the format of file0, file1, file2, etc.", path_string)
@@ -60,75 +66,307 @@ impl Client {
       inputs
     }
 
-    fn pad_files(&self, files: &mut Vec<String>) {
-      let num_files = files.len();
-      let num_files_to_pad = num_files.next_power_of_two() - num_files;
-      files.append(&mut vec![String::from("0");num_files_to_pad]);
-    }
-
-    // Read and send files to server
-    pub fn store(&self) {
+    // Read and send files to server. Salted files have leaves which reveal nothing about their
+    // contents to anyone holding proofs for other files
+    pub fn store(&self, salted: bool) {
       println!("Sending all files in files/ directory to server for storage.");
       
-      let mut files = self.read_files();
+      let files = self.read_files();
       let num_files = files.len();
-
-      // Pad files to be vector of length which equals a power of two
-      self.pad_files(&mut files);
+      if num_files == 0 {
+        panic!("No files found in {}/ directory", FILES_DIR_NAME);
+      }
 
       // Hash all files to send along with the files themselves
-      let hashes: Vec<String> = files
-          .clone()
-          .into_iter()
-          .map(|x: String| hash(x.as_ref()))
-          .collect();
+      let salts = if salted { Some(random_salts(num_files)) } else { None };
+      let hashes: Vec<Digest> = salt_hashes(hash_files(&files, STORE_SCHEME, STORE_CHUNK_SIZE), salts.as_deref());
 
       let input: StoreRequest = StoreRequest {
           files: files.clone(),
           hashes: hashes.clone(),
+          scheme: STORE_SCHEME,
+          chunk_size: STORE_CHUNK_SIZE,
+          salts,
       };
       let response: StoreResponse = self.post("store", &input);
 
-      // Persist root hash and number of files 
-      println!("Writing Merlke root hash to local storage.");
-      let client_storage_data = ClientStoredData {
+      // Check the returned root against the root computed from the same hashes locally
+      let mut root_builder = MerkleRootBuilder::<Sha256>::new(STORE_SCHEME);
+      root_builder.extend(hashes);
+      if root_builder.finalize() != Some(response.root) {
+        panic!("Root returned by the server does not match the files sent!")
+      }
+
+      let mut client_storage_data = ClientStoredData {
         root_hash: response.root,
-        num_files
+        num_files,
+        scheme: STORE_SCHEME,
+        chunk_size: STORE_CHUNK_SIZE,
+        salted,
+        receipts: Vec::new(),
+      };
+      println!("Verifying server's signature over the root.");
+      verify_tree_head(&response.signed_tree_head, &client_storage_data);
+
+      // Persist root hash and number of files along with the signed tree head as a receipt
+      println!("Writing Merlke root hash to local storage.");
+      client_storage_data.receipts.push(response.signed_tree_head);
+      write_client_storage_data(&client_storage_data);
+      println!("Done.");
+    }
+
+    // Send files in files/ directory which the server does not yet have, to be added to the end of the stored files
+    pub fn append(&self) {
+      let mut client_storage_data = read_client_storage_data();
+      let files = self.read_files();
+      if files.len() <= client_storage_data.num_files {
+        println!("No new files in files/ directory to send.");
+        return;
+      }
+      let new_files = files[client_storage_data.num_files..].to_vec();
+      println!("Sending {} new files in files/ directory to server for storage.", new_files.len());
+
+      let salts = if client_storage_data.salted { Some(random_salts(new_files.len())) } else { None };
+      let hashes: Vec<Digest> = salt_hashes(hash_files(&new_files, client_storage_data.scheme, client_storage_data.chunk_size), salts.as_deref());
+      let input = AppendRequest {
+          files: new_files,
+          hashes,
+          salts,
+      };
+      let response: AppendResponse = self.post("append", &input);
+
+      println!("Verifying that previously stored files are unchanged against local root hash record.");
+      if !verify_consistency::<Sha256>(
+        &client_storage_data.root_hash,
+        client_storage_data.num_files,
+        &response.root,
+        files.len(),
+        client_storage_data.scheme,
+        &response.consistency_proof,
+      ) {
+        panic!("Consistency proof failed - the server may have changed previously stored files!")
+      }
+
+      client_storage_data.root_hash = response.root;
+      client_storage_data.num_files = files.len();
+      println!("Verifying server's signature over the new root.");
+      verify_tree_head(&response.signed_tree_head, &client_storage_data);
+
+      println!("Writing Merlke root hash to local storage.");
+      client_storage_data.receipts.push(response.signed_tree_head);
+      write_client_storage_data(&client_storage_data);
+      println!("Done.");
+    }
+
+    // Send the current contents of one file in files/ directory to replace the stored file at the same index
+    pub fn update(&self, file_index: usize) {
+      let mut client_storage_data = read_client_storage_data();
+      if file_index > client_storage_data.num_files - 1 {
+        panic!("Cannot update file with index {}. Only {} files stored. Files are 0-indexed.", file_index, client_storage_data.num_files);
+      }
+      let files = self.read_files();
+      let file = files[file_index].clone();
+      // A new salt, so the old and new leaves cannot be linked
+      let salt = if client_storage_data.salted { Some(random_salts(1)[0]) } else { None };
+      let file_hash = hash_file(&file, &client_storage_data);
+      let file_hash = match &salt {
+        Some(salt) => salt_leaf::<Sha256>(salt, &file_hash),
+        None => file_hash,
       };
-      SimpleStringDb::new().write_data_to_file(ROOT_STORAGE_FILE_NAME, &build_client_storage_data(&client_storage_data));
+
+      println!("Sending file with index {} to server to replace the stored file.", file_index);
+      let response: UpdateResponse = self.post("update", &UpdateRequest { file_index, file, hash: file_hash, salt });
+
+      println!("Verifying that only file with index {} changed against local root hash record.", file_index);
+      let update = &response.update;
+      if update.index != file_index
+        || update.leaf_count != client_storage_data.num_files
+        || !verify_update::<Sha256>(&client_storage_data.root_hash, client_storage_data.scheme, &file_hash, update)
+      {
+        panic!("Update proof failed - the server may have changed other files!")
+      }
+
+      client_storage_data.root_hash = update.new_root;
+      println!("Verifying server's signature over the new root.");
+      verify_tree_head(&response.signed_tree_head, &client_storage_data);
+
+      println!("Writing Merlke root hash to local storage.");
+      client_storage_data.receipts.push(response.signed_tree_head);
+      write_client_storage_data(&client_storage_data);
       println!("Done.");
     }
 
     pub fn fetch(&self, file_index: usize) {
+      // Retreive root hash and number of files stored from local storage
+      let client_storage_data: ClientStoredData = read_client_storage_data();
+      if file_index > client_storage_data.num_files - 1 {
+        panic!("Cannot fetch file with index {}. Only {} files stored. Files are 0-indexed.", file_index, client_storage_data.num_files);
+      }
+
       println!("Fetching file from server.");
       let response: FetchResponse = self.get("fetch", &FetchRequest { file_index });
 
       println!("Verifying file and Merkle proof against local root hash record.");
-      // Retreive root hash and number of files stored from local storage
-      let client_storage_data: ClientStoredData = parse_client_storage_data(&SimpleStringDb::new().read_data_from_file(ROOT_STORAGE_FILE_NAME));
+      self.verify(&response, file_index, &client_storage_data);
+    }
+
+    pub fn verify(&self, fetch_response: &FetchResponse, file_index: usize, client_storage_data: &ClientStoredData) {
+      // Re-hash the returned file to validate integrity, with the salt returned with it if the files are salted
+      let file_hash = hash_file(&fetch_response.file, client_storage_data);
+      let file_hash = match (client_storage_data.salted, &fetch_response.salt) {
+        (true, Some(salt)) => salt_leaf::<Sha256>(salt, &file_hash),
+        (false, None) => file_hash,
+        _ => panic!("File succesfully retrieved but its salt does not match the local record - the file may have been tampered with!")
+      };
+      // Feed re-hashed file along with merkle root into the proof, which must be for the requested
+      // index in a tree of all stored files built with the recorded scheme, to verify that the
+      // file returned is the one stored at that index
+      let proof = &fetch_response.proof;
+      let valid_proof = proof.index == file_index
+        && proof.leaf_count == client_storage_data.num_files
+        && proof.verify::<Sha256>(&client_storage_data.root_hash, client_storage_data.scheme, &file_hash);
+      if !valid_proof {
+        panic!("File succesfully retrieved but proof failed - the file may have been tampered with!")
+      }
+      verify_tree_head(&fetch_response.signed_tree_head, client_storage_data);
+      println!("Successfully retreived file with index {} and verified Merkle proof. File contents: {}", file_index, fetch_response.file);
+    }
+
+    // Fetch bytes start..end of a stored file, which the server sends as the whole chunks holding them
+    pub fn fetch_range(&self, file_index: usize, start: usize, end: usize) {
+      let client_storage_data: ClientStoredData = read_client_storage_data();
       if file_index > client_storage_data.num_files - 1 {
         panic!("Cannot fetch file with index {}. Only {} files stored. Files are 0-indexed.", file_index, client_storage_data.num_files);
       }
+      let chunk_size = match client_storage_data.chunk_size {
+        Some(chunk_size) => chunk_size,
+        None => panic!("Files were stored without a chunk size, so ranges of them cannot be fetched. Store them again to fetch ranges.")
+      };
 
-      self.verify(&response, &client_storage_data.root_hash);
-    }
+      println!("Fetching bytes {}..{} of file from server.", start, end);
+      let response: FetchRangeResponse = self.get("fetch-range", &FetchRangeRequest { file_index, start, end });
 
-    pub fn verify(&self, fetch_response: &FetchResponse, root_hash: &String) {
-      // Re-hash the returned file to validate integrity
-      let file_hash = hash(&fetch_response.file.as_ref());
-      // Feed re-hashed file along with merkle root and proof in to verify
-      let valid_proof = verify(root_hash, &file_hash, &fetch_response.proof);
+      println!("Verifying chunks and Merkle proofs against local root hash record.");
+      // The chunks must be for the requested index in a tree of all stored files built with the
+      // recorded scheme, split with the recorded chunk size, and must hold the whole range
+      let proof = &response.proof;
+      // Offsets which overflow cannot be in any file
+      let chunks = proof.chunks.first_chunk.checked_mul(chunk_size)
+        .and_then(|chunks_start| Some((chunks_start, chunks_start.checked_add(response.data.len())?)));
+      let (chunks_start, chunks_end) = match chunks {
+        Some(chunks) => chunks,
+        None => panic!("Chunks succesfully retrieved but proof failed - the file may have been tampered with!")
+      };
+      let valid_proof = proof.file.index == file_index
+        && proof.file.leaf_count == client_storage_data.num_files
+        && proof.chunks.chunk_size == chunk_size
+        && proof.salt.is_some() == client_storage_data.salted
+        && chunks_start <= start
+        && end <= chunks_end
+        && proof.verify::<Sha256>(&client_storage_data.root_hash, client_storage_data.scheme, &response.data);
       if !valid_proof {
-        panic!("File succesfully retrieved but proof failed - the file may have been tampered with!")
+        panic!("Chunks succesfully retrieved but proof failed - the file may have been tampered with!")
       }
-      println!("Successfully retreived file with index and verified Merkle proof. File contents: {}", fetch_response.file);
+      verify_tree_head(&response.signed_tree_head, &client_storage_data);
+      let range = &response.data[start - chunks_start..end - chunks_start];
+      println!("Successfully retreived bytes {}..{} of file with index {} and verified Merkle proofs. Contents: {}", start, end, file_index, String::from_utf8_lossy(range));
     }
 }
 
+// Leaves for files, which are the roots of chunk trees if the files are stored with a chunk size
+fn hash_files(files: &[String], scheme: Scheme, chunk_size: Option<usize>) -> Vec<Digest> {
+  match chunk_size {
+    Some(chunk_size) => files.iter()
+      .map(|file| chunked_file_leaf(file, chunk_size))
+      .collect(),
+    None => scheme.hash_leaves::<Sha256, String>(files),
+  }
+}
+
+// Leaf of a file hashed as a chunk tree. A chunk size of 0 can only come from an edited root.db
+fn chunked_file_leaf(file: &str, chunk_size: usize) -> Digest {
+  file_leaf_from_reader::<Sha256>(file.as_bytes(), chunk_size)
+    .unwrap_or_else(|error| panic!("Cannot hash file in chunks of {} bytes: {}", chunk_size, error))
+}
+
+// Salt each leaf with the salt of its file, if there are salts
+fn salt_hashes(hashes: Vec<Digest>, salts: Option<&[Salt]>) -> Vec<Digest> {
+  match salts {
+    Some(salts) => hashes.iter().zip(salts).map(|(hash, salt)| salt_leaf::<Sha256>(salt, hash)).collect(),
+    None => hashes,
+  }
+}
+
+fn random_salts(count: usize) -> Vec<Salt> {
+  (0..count).map(|_| {
+    let mut salt = [0u8; SALT_LEN];
+    getrandom::getrandom(&mut salt).unwrap();
+    Salt(salt)
+  }).collect()
+}
+
+fn hash_file(file: &str, client_storage_data: &ClientStoredData) -> Digest {
+  match client_storage_data.chunk_size {
+    Some(chunk_size) => chunked_file_leaf(file, chunk_size),
+    None => client_storage_data.scheme.hash_leaf::<Sha256>(file.as_ref()),
+  }
+}
+
+// Check that the server signed a head for the tree in the local record with the pinned key
+fn verify_tree_head(signed_tree_head: &SignedTreeHead, client_storage_data: &ClientStoredData) {
+  let tree_head = &signed_tree_head.tree_head;
+  let valid_tree_head = tree_head.root == client_storage_data.root_hash
+    && tree_head.leaf_count == client_storage_data.num_files
+    && tree_head.scheme == client_storage_data.scheme
+    && tree_head.hash_id == Sha256::ID
+    && signed_tree_head.verify(&read_server_public_key());
+  if !valid_tree_head {
+    panic!("Tree head signature failed - the server did not sign the local root hash record with the pinned key!")
+  }
+}
+
+fn read_server_public_key() -> VerifyingKey {
+  let hex = match fs::read_to_string(SERVER_KEY_FILE_NAME) {
+    Ok(hex) => hex,
+    Err(_) => panic!("Server public key not pinned. Copy {} from the server's directory to this one.", SERVER_KEY_FILE_NAME)
+  };
+  let public_key = base16ct::mixed::decode_vec(hex.trim()).ok()
+    .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+    .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+  match public_key {
+    Some(public_key) => public_key,
+    None => panic!("{} is not a hex encoded Ed25519 public key", SERVER_KEY_FILE_NAME)
+  }
+}
+
+// Body of a response, which the server sends as an error message rather than JSON when it rejects a request
+fn read_response(response: reqwest::blocking::Response) -> String {
+  let status = response.status();
+  let value = response.text().unwrap();
+  if !status.is_success() {
+    panic!("Server rejected request ({}): {}", status, value)
+  }
+  value
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ClientStoredData {
-  root_hash: String,
-  num_files: usize
+  root_hash: Digest,
+  num_files: usize,
+  // Records written before schemes existed used the legacy sorted scheme
+  #[serde(default)]
+  scheme: Scheme,
+  // Records written before chunk trees existed hashed each file whole
+  #[serde(default)]
+  chunk_size: Option<usize>,
+  // Whether each leaf is salted with a salt the server returns with the file. Records written
+  // before salts existed were not salted
+  #[serde(default)]
+  salted: bool,
+  // Tree heads the server signed for each root recorded, oldest first, as proof that it committed
+  // to them. Records written before tree heads were signed have none
+  #[serde(default)]
+  receipts: Vec<SignedTreeHead>,
 }
 
 // Vec of root hash and total number of stored files
@@ -140,23 +378,10 @@ fn parse_client_storage_data(data: &str) -> ClientStoredData {
   serde_json::from_str(data).unwrap()
 }
 
-#[test]
-fn test_file_padding() {
-  let client = Client {
-    server_end_point: String::from("")
-  };
-  // Should pad to length 8
-  let mut files = vec!["0".to_string();6];
-  client.pad_files(&mut files);
-  assert_eq!(files.len(), 8);
-
-  // Should not pad
-  files = vec!["0".to_string();8];
-  client.pad_files(&mut files);
-  assert_eq!(files.len(), 8);
-
-  // Should pad to length 16
-  files = vec!["0".to_string();9];
-  client.pad_files(&mut files);
-  assert_eq!(files.len(), 16);
-}
\ No newline at end of file
+fn read_client_storage_data() -> ClientStoredData {
+  parse_client_storage_data(&SimpleStringDb::new().read_data_from_file(ROOT_STORAGE_FILE_NAME))
+}
+
+fn write_client_storage_data(client_stored_data: &ClientStoredData) {
+  SimpleStringDb::new().write_data_to_file(ROOT_STORAGE_FILE_NAME, &build_client_storage_data(client_stored_data))
+}
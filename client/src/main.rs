@@ -9,7 +9,11 @@ use std::env;
 use client::Client;
 
 static PERSIST_FILES_CMD: &str = "persist-files";
+static APPEND_FILES_CMD: &str = "append-files";
 static RETRIEVE_FILE_CMD: &str = "retrieve-file";
+static RETRIEVE_RANGE_CMD: &str = "retrieve-range";
+static UPDATE_FILE_CMD: &str = "update-file";
+static SALTED_FLAG: &str = "--salted";
 
 fn main() {
     // init
@@ -22,15 +26,33 @@ fn main() {
     // Handle command
     let cmd = &args[2];
     if cmd == PERSIST_FILES_CMD {
-        // Store contents of items in files/ directory
-        client.store();
+        // Store contents of items in files/ directory, salting their leaves if asked to
+        let salted = args.get(3).map(String::as_str) == Some(SALTED_FLAG);
+        client.store(salted);
+    } else if cmd == APPEND_FILES_CMD {
+        // Send only the items in files/ directory which have been added since they were last stored
+        client.append();
     } else if cmd == RETRIEVE_FILE_CMD {
         if args.len() < 4 {
             panic!("Please provide a file index to retrieve: eg cargo run -- http://127.0.0.1:8000 retrieve-files 4")
         }
         let file_index = &args[3];
         client.fetch(file_index.parse::<usize>().unwrap());
+    } else if cmd == RETRIEVE_RANGE_CMD {
+        if args.len() < 6 {
+            panic!("Please provide a file index and the start and end of a byte range to retrieve: eg cargo run -- http://127.0.0.1:8000 retrieve-range 4 100 200")
+        }
+        let file_index = &args[3];
+        let start = &args[4];
+        let end = &args[5];
+        client.fetch_range(file_index.parse::<usize>().unwrap(), start.parse::<usize>().unwrap(), end.parse::<usize>().unwrap());
+    } else if cmd == UPDATE_FILE_CMD {
+        if args.len() < 4 {
+            panic!("Please provide a file index to update: eg cargo run -- http://127.0.0.1:8000 update-file 4")
+        }
+        let file_index = &args[3];
+        client.update(file_index.parse::<usize>().unwrap());
     } else {
-        panic!("Please pass a valid command: `persist-files` or `retrieve-file $INDEX`")
+        panic!("Please pass a valid command: `persist-files [--salted]`, `append-files`, `update-file $INDEX`, `retrieve-file $INDEX` or `retrieve-range $INDEX $START $END`")
     }
 }
@@ -0,0 +1,446 @@
+#[cfg(feature = "std")]
+use crate::{salt_leaf, verify_many, MerkleTree, MultiProof, Proof, Salt};
+use crate::{Digest, MerkleError, MerkleHasher, MerkleRootBuilder, Scheme};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+/// Chunk size for files which do not choose another
+pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Scheme used to build every chunk tree
+const CHUNK_SCHEME: Scheme = Scheme::DomainSeparated;
+/// Prefix for the root of a chunk tree hashed into the file's leaf in the collection tree, distinct
+/// from the prefixes of `Scheme::DomainSeparated` leaves and nodes
+const FILE_PREFIX: u8 = 0x02;
+
+/// Merkle tree over the fixed size chunks of one file
+///
+/// The file's leaf in the collection tree commits to the chunk size, the file length and the root
+/// of this tree, so a range of the file can be proven without the rest of it. An empty file has a
+/// single empty chunk.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ChunkTree<H: MerkleHasher> {
+    pub chunk_size: usize,
+    pub file_len: usize,
+    pub tree: MerkleTree<H>,
+}
+
+/// Proof that some whole chunks of a file are in the file with a given leaf
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChunkRangeProof {
+    pub chunk_size: usize,
+    pub file_len: usize,
+    pub chunk_root: Digest,
+    /// Index of the first chunk proven
+    pub first_chunk: usize,
+    pub proof: MultiProof,
+}
+
+/// Proof that some whole chunks of a file are in the file at an index of a collection
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileRangeProof {
+    pub chunks: ChunkRangeProof,
+    pub file: Proof,
+    /// Salt of the file's leaf in the collection, if the collection is salted
+    #[serde(default)]
+    pub salt: Option<Salt>,
+}
+
+#[cfg(feature = "std")]
+impl<H: MerkleHasher> ChunkTree<H> {
+    /// Split a file into chunks of `chunk_size` bytes and build a tree over them
+    ///
+    /// Panics if the chunk size is 0. See `try_build`.
+    pub fn build(data: &[u8], chunk_size: usize) -> ChunkTree<H> {
+        Self::try_build(data, chunk_size).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `build`, but returns an error rather than panicking if the chunk size is 0
+    pub fn try_build(data: &[u8], chunk_size: usize) -> Result<ChunkTree<H>, MerkleError> {
+        if chunk_size == 0 {
+            return Err(MerkleError::InvalidChunkSize);
+        }
+        let leaves: Vec<Digest> = if data.is_empty() {
+            Vec::from([CHUNK_SCHEME.hash_leaf::<H>(&[])])
+        } else {
+            data.chunks(chunk_size)
+                .map(|chunk| CHUNK_SCHEME.hash_leaf::<H>(chunk))
+                .collect()
+        };
+        Ok(ChunkTree {
+            chunk_size,
+            file_len: data.len(),
+            tree: MerkleTree::try_build_with_scheme(&leaves, CHUNK_SCHEME)?,
+        })
+    }
+
+    /// The file's leaf in the collection tree
+    pub fn file_leaf(&self) -> Digest {
+        hash_file_leaf::<H>(self.chunk_size, self.file_len, &self.tree.get_root())
+    }
+
+    pub fn num_chunks(&self) -> usize {
+        self.tree.num_leaves
+    }
+
+    /// Create a proof for the chunks holding the bytes `start..end` of the file
+    ///
+    /// The verifier needs the whole chunks, which are the bytes in `chunk_byte_range(start, end)`.
+    pub fn try_prove_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<ChunkRangeProof, MerkleError> {
+        let (first_chunk, last_chunk) = self.chunk_range(start, end)?;
+        let indices: Vec<usize> = (first_chunk..=last_chunk).collect();
+        Ok(ChunkRangeProof {
+            chunk_size: self.chunk_size,
+            file_len: self.file_len,
+            chunk_root: self.tree.get_root(),
+            first_chunk,
+            proof: self.tree.prove_many(&indices),
+        })
+    }
+
+    /// Bytes of the file making up the whole chunks which hold the bytes `start..end`
+    pub fn chunk_byte_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<(usize, usize), MerkleError> {
+        let (first_chunk, last_chunk) = self.chunk_range(start, end)?;
+        Ok((
+            first_chunk * self.chunk_size,
+            ((last_chunk + 1) * self.chunk_size).min(self.file_len),
+        ))
+    }
+
+    /// First and last chunks holding the bytes `start..end`. An empty range at the start of the
+    /// file is held by the first chunk
+    fn chunk_range(&self, start: usize, end: usize) -> Result<(usize, usize), MerkleError> {
+        if start > end || end > self.file_len || (start == end && start != 0) {
+            return Err(MerkleError::RangeOutOfBounds {
+                start,
+                end,
+                len: self.file_len,
+            });
+        }
+        let first_chunk = start / self.chunk_size;
+        let last_chunk = (end.max(1) - 1) / self.chunk_size;
+        Ok((first_chunk, last_chunk))
+    }
+}
+
+#[cfg(feature = "std")]
+impl ChunkRangeProof {
+    /// Take a file's leaf and the bytes of the proven chunks and return true if they are those
+    /// chunks of the file
+    pub fn verify<H: MerkleHasher>(&self, file_leaf: &Digest, data: &[u8]) -> bool {
+        if self.chunk_size == 0
+            || self.proof.scheme != CHUNK_SCHEME
+            || self.proof.leaf_count != self.file_len.div_ceil(self.chunk_size).max(1)
+            || hash_file_leaf::<H>(self.chunk_size, self.file_len, &self.chunk_root) != *file_leaf
+        {
+            return false;
+        }
+        // The chunks run from the first chunk to the end of the data, which must end on a chunk
+        // boundary or at the end of the file. Offsets which overflow are past the end of any file
+        let Some(start) = self.first_chunk.checked_mul(self.chunk_size) else {
+            return false;
+        };
+        let Some(end) = start.checked_add(data.len()) else {
+            return false;
+        };
+        if end > self.file_len
+            || (end != self.file_len && !data.len().is_multiple_of(self.chunk_size))
+            || (data.is_empty() && self.file_len != 0)
+        {
+            return false;
+        }
+        let leaves: Vec<(usize, Digest)> = if data.is_empty() {
+            Vec::from([(self.first_chunk, CHUNK_SCHEME.hash_leaf::<H>(&[]))])
+        } else {
+            data.chunks(self.chunk_size)
+                .enumerate()
+                .map(|(i, chunk)| (self.first_chunk + i, CHUNK_SCHEME.hash_leaf::<H>(chunk)))
+                .collect()
+        };
+        verify_many::<H>(&self.chunk_root, CHUNK_SCHEME, &leaves, &self.proof)
+    }
+}
+
+#[cfg(feature = "std")]
+impl FileRangeProof {
+    /// Take a collection root, the scheme the collection tree was built with and the bytes of the
+    /// proven chunks and return true if they are those chunks of the file at the proof's index of
+    /// the collection
+    pub fn verify<H: MerkleHasher>(&self, root_hash: &Digest, scheme: Scheme, data: &[u8]) -> bool {
+        let file_leaf = hash_file_leaf::<H>(
+            self.chunks.chunk_size,
+            self.chunks.file_len,
+            &self.chunks.chunk_root,
+        );
+        let collection_leaf = match &self.salt {
+            Some(salt) => salt_leaf::<H>(salt, &file_leaf),
+            None => file_leaf,
+        };
+        self.chunks.verify::<H>(&file_leaf, data)
+            && self.file.verify::<H>(root_hash, scheme, &collection_leaf)
+    }
+}
+
+/// Hash a whole file in chunks of `chunk_size` bytes into its leaf in the collection tree, as
+/// `ChunkTree::file_leaf` does, without building the tree
+///
+/// Returns `MerkleError::InvalidChunkSize` if the chunk size is 0.
+pub fn file_leaf<H: MerkleHasher>(data: &[u8], chunk_size: usize) -> Result<Digest, MerkleError> {
+    if chunk_size == 0 {
+        return Err(MerkleError::InvalidChunkSize);
+    }
+    let mut root_builder = MerkleRootBuilder::<H>::new(CHUNK_SCHEME);
+    // An empty file has a single empty chunk
+    if data.is_empty() {
+        root_builder.push(CHUNK_SCHEME.hash_leaf::<H>(&[]));
+    }
+    root_builder.extend(
+        data.chunks(chunk_size)
+            .map(|chunk| CHUNK_SCHEME.hash_leaf::<H>(chunk)),
+    );
+    Ok(hash_file_leaf::<H>(
+        chunk_size,
+        data.len(),
+        &root_builder.finalize().unwrap(),
+    ))
+}
+
+#[cfg(feature = "std")]
+/// Compute the leaf of a file split into chunks of `chunk_size` bytes, reading it once and holding
+/// only one chunk and O(log n) subtree roots in memory
+///
+/// A chunk size of 0 is an `InvalidInput` error wrapping `MerkleError::InvalidChunkSize`.
+pub fn file_leaf_from_reader<H: MerkleHasher>(
+    mut reader: impl Read,
+    chunk_size: usize,
+) -> io::Result<Digest> {
+    if chunk_size == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            MerkleError::InvalidChunkSize,
+        ));
+    }
+    let mut root_builder = MerkleRootBuilder::<H>::new(CHUNK_SCHEME);
+    let mut chunk = vec![0u8; chunk_size];
+    let mut file_len = 0;
+    loop {
+        // Fill a whole chunk unless the file ends first
+        let mut filled = 0;
+        while filled < chunk_size {
+            match reader.read(&mut chunk[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        if filled > 0 || file_len == 0 {
+            root_builder.push(CHUNK_SCHEME.hash_leaf::<H>(&chunk[..filled]));
+        }
+        file_len += filled;
+        if filled < chunk_size {
+            break;
+        }
+    }
+    Ok(hash_file_leaf::<H>(
+        chunk_size,
+        file_len,
+        &root_builder.finalize().unwrap(),
+    ))
+}
+
+fn hash_file_leaf<H: MerkleHasher>(
+    chunk_size: usize,
+    file_len: usize,
+    chunk_root: &Digest,
+) -> Digest {
+    H::hash_parts(&[
+        &[FILE_PREFIX],
+        &(chunk_size as u64).to_le_bytes(),
+        &(file_len as u64).to_le_bytes(),
+        chunk_root.as_bytes(),
+    ])
+}
+
+#[cfg(all(test, feature = "std", feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::Sha256;
+
+    fn file(len: usize) -> Vec<u8> {
+        (0..len).map(|x| (x % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_file_leaf_from_reader_matches_build() {
+        for len in [0, 1, 99, 100, 101, 1000, 1234] {
+            let data = file(len);
+            let chunk_tree = ChunkTree::<Sha256>::build(&data, 100);
+            assert_eq!(chunk_tree.num_chunks(), len.div_ceil(100).max(1));
+            assert_eq!(
+                file_leaf_from_reader::<Sha256>(data.as_slice(), 100).unwrap(),
+                chunk_tree.file_leaf()
+            );
+            assert_eq!(
+                file_leaf::<Sha256>(&data, 100).unwrap(),
+                chunk_tree.file_leaf()
+            );
+        }
+        // The leaf commits to the chunk size
+        assert_ne!(
+            ChunkTree::<Sha256>::build(&file(1000), 100).file_leaf(),
+            ChunkTree::<Sha256>::build(&file(1000), 200).file_leaf()
+        );
+    }
+
+    #[test]
+    fn test_zero_chunk_size() {
+        assert_eq!(
+            ChunkTree::<Sha256>::try_build(&file(10), 0).unwrap_err(),
+            MerkleError::InvalidChunkSize
+        );
+        assert_eq!(
+            file_leaf::<Sha256>(&file(10), 0),
+            Err(MerkleError::InvalidChunkSize)
+        );
+        let error = file_leaf_from_reader::<Sha256>(file(10).as_slice(), 0).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            error.into_inner().unwrap().downcast_ref::<MerkleError>(),
+            Some(&MerkleError::InvalidChunkSize)
+        );
+    }
+
+    #[test]
+    fn test_range_proofs() {
+        for len in [0, 1, 100, 250, 1000] {
+            let data = file(len);
+            let chunk_tree = ChunkTree::<Sha256>::build(&data, 100);
+            let file_leaf = chunk_tree.file_leaf();
+            for start in (0..=len).step_by(37) {
+                for end in (start..=len).step_by(29) {
+                    if start == end && start != 0 {
+                        continue;
+                    }
+                    let proof = chunk_tree.try_prove_range(start, end).unwrap();
+                    let (chunks_start, chunks_end) =
+                        chunk_tree.chunk_byte_range(start, end).unwrap();
+                    assert!(chunks_start <= start && end <= chunks_end);
+                    assert!(proof.verify::<Sha256>(&file_leaf, &data[chunks_start..chunks_end]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_proof_rejects_wrong_data() {
+        let data = file(1000);
+        let chunk_tree = ChunkTree::<Sha256>::build(&data, 100);
+        let file_leaf = chunk_tree.file_leaf();
+        let proof = chunk_tree.try_prove_range(150, 420).unwrap();
+        assert_eq!(chunk_tree.chunk_byte_range(150, 420), Ok((100, 500)));
+        assert!(proof.verify::<Sha256>(&file_leaf, &data[100..500]));
+
+        let mut tampered = data[100..500].to_vec();
+        tampered[200] ^= 1;
+        assert!(!proof.verify::<Sha256>(&file_leaf, &tampered));
+        // Missing the last chunk, or part of it
+        assert!(!proof.verify::<Sha256>(&file_leaf, &data[100..400]));
+        assert!(!proof.verify::<Sha256>(&file_leaf, &data[100..450]));
+        // Claiming a different file length
+        let mut other_len = proof.clone();
+        other_len.file_len = 999;
+        assert!(!other_len.verify::<Sha256>(&file_leaf, &data[100..500]));
+
+        assert_eq!(
+            chunk_tree.try_prove_range(500, 1001),
+            Err(MerkleError::RangeOutOfBounds {
+                start: 500,
+                end: 1001,
+                len: 1000
+            })
+        );
+        assert!(chunk_tree.try_prove_range(500, 400).is_err());
+        assert!(chunk_tree.try_prove_range(500, 500).is_err());
+    }
+
+    #[test]
+    fn test_range_proof_rejects_overflowing_offsets() {
+        let data = file(1000);
+        let chunk_tree = ChunkTree::<Sha256>::build(&data, 100);
+        let file_leaf = chunk_tree.file_leaf();
+        let mut proof = chunk_tree.try_prove_range(0, 150).unwrap();
+        assert!(proof.verify::<Sha256>(&file_leaf, &data[..200]));
+        // The offset of the first chunk overflows
+        proof.first_chunk = usize::MAX;
+        assert!(!proof.verify::<Sha256>(&file_leaf, &data[..200]));
+        // The offset fits but the end of the data does not
+        proof.first_chunk = usize::MAX / 100;
+        assert!(!proof.verify::<Sha256>(&file_leaf, &data[..200]));
+    }
+
+    #[test]
+    fn test_combined_with_collection_proof() {
+        let files: Vec<Vec<u8>> = (0..5).map(|x| file(300 + x * 70)).collect();
+        let chunk_trees: Vec<ChunkTree<Sha256>> = files
+            .iter()
+            .map(|data| ChunkTree::build(data, 64))
+            .collect();
+        let leaves: Vec<Digest> = chunk_trees.iter().map(|tree| tree.file_leaf()).collect();
+        let collection = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        let root = collection.get_root();
+
+        let proof = FileRangeProof {
+            chunks: chunk_trees[3].try_prove_range(130, 200).unwrap(),
+            file: collection.prove_leaf(3),
+            salt: None,
+        };
+        assert!(proof.verify::<Sha256>(&root, Scheme::LATEST, &files[3][128..256]));
+        // The same chunks claimed for another file
+        let mut other_file = proof.clone();
+        other_file.file = collection.prove_leaf(2);
+        assert!(!other_file.verify::<Sha256>(&root, Scheme::LATEST, &files[3][128..256]));
+    }
+
+    #[test]
+    fn test_combined_with_salted_collection_proof() {
+        let files: Vec<Vec<u8>> = (0..5).map(|x| file(300 + x * 70)).collect();
+        let salts: Vec<Salt> = (0..5).map(|x| Salt([x; 32])).collect();
+        let chunk_trees: Vec<ChunkTree<Sha256>> = files
+            .iter()
+            .map(|data| ChunkTree::build(data, 64))
+            .collect();
+        let leaves: Vec<Digest> = chunk_trees
+            .iter()
+            .zip(&salts)
+            .map(|(tree, salt)| salt_leaf::<Sha256>(salt, &tree.file_leaf()))
+            .collect();
+        let collection = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        let root = collection.get_root();
+
+        let proof = FileRangeProof {
+            chunks: chunk_trees[1].try_prove_range(0, 10).unwrap(),
+            file: collection.prove_leaf(1),
+            salt: Some(salts[1]),
+        };
+        assert!(proof.verify::<Sha256>(&root, Scheme::LATEST, &files[1][..64]));
+        // Without the salt, or with another file's, the chunks are not tied to the collection
+        let mut unsalted = proof.clone();
+        unsalted.salt = None;
+        assert!(!unsalted.verify::<Sha256>(&root, Scheme::LATEST, &files[1][..64]));
+        let mut wrong_salt = proof;
+        wrong_salt.salt = Some(salts[2]);
+        assert!(!wrong_salt.verify::<Sha256>(&root, Scheme::LATEST, &files[1][..64]));
+    }
+}
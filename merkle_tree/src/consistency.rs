@@ -0,0 +1,320 @@
+use crate::{Digest, MerkleError, MerkleHasher, MerkleTree, Scheme};
+
+/// Proof that a tree is an earlier version of another with leaves only appended, as described in
+/// RFC 6962 section 2.1.2 and RFC 9162 section 2.1.4
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    pub scheme: Scheme,
+    pub nodes: Vec<Digest>,
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Create a proof that the first `old_size` leaves of this tree are unchanged since the tree had
+    /// that many leaves
+    ///
+    /// Panics unless `old_size` is between 1 and the number of leaves. See `try_prove_consistency`.
+    pub fn prove_consistency(&self, old_size: usize) -> ConsistencyProof {
+        self.try_prove_consistency(old_size)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `prove_consistency`, but returns an error rather than panicking if `old_size` is 0 or
+    /// more than the number of leaves
+    pub fn try_prove_consistency(&self, old_size: usize) -> Result<ConsistencyProof, MerkleError> {
+        if old_size == 0 || old_size > self.num_leaves {
+            return Err(MerkleError::OldSizeOutOfRange {
+                old_size,
+                leaf_count: self.num_leaves,
+            });
+        }
+        let mut nodes = Vec::new();
+        self.consistency_subproof(old_size, 0, self.num_leaves, true, &mut nodes);
+        Ok(ConsistencyProof {
+            scheme: self.scheme,
+            nodes,
+        })
+    }
+
+    /// SUBPROOF(m, D[start:end], b) from RFC 6962 section 2.1.2
+    fn consistency_subproof(
+        &self,
+        old_size: usize,
+        start: usize,
+        end: usize,
+        old_is_whole_subtree: bool,
+        nodes: &mut Vec<Digest>,
+    ) {
+        let size = end - start;
+        if old_size == size {
+            if !old_is_whole_subtree {
+                nodes.push(self.subtree_hash(start, end));
+            }
+            return;
+        }
+        // Largest power of 2 smaller than size
+        let split = 1 << (size - 1).ilog2();
+        if old_size <= split {
+            self.consistency_subproof(old_size, start, start + split, old_is_whole_subtree, nodes);
+            nodes.push(self.subtree_hash(start + split, end));
+        } else {
+            self.consistency_subproof(old_size - split, start + split, end, false, nodes);
+            nodes.push(self.subtree_hash(start, start + split));
+        }
+    }
+
+    /// Hash of the subtree over leaves `start..end`
+    ///
+    /// The range must be a node of the tree: `start` a multiple of the smallest power of 2 no less
+    /// than its length, and `end` either that far past `start` or the end of the tree.
+    fn subtree_hash(&self, start: usize, end: usize) -> Digest {
+        let row = (end - start).next_power_of_two().ilog2() as usize;
+        self.tree[row][start >> row]
+    }
+}
+
+/// Take the root and size of an earlier tree, the root and size of a later tree, the scheme both
+/// were built with and a consistency proof and return true if the later tree only appends leaves
+/// to the earlier one
+///
+/// The scheme must be the one the verifier recorded, not one taken from the proof. Otherwise a
+/// server could prove consistency under a weaker scheme, such as `Scheme::Sorted`, which ignores
+/// the order of children.
+///
+/// This follows the verification algorithm of RFC 9162 section 2.1.4.2.
+pub fn verify_consistency<H: MerkleHasher>(
+    old_root: &Digest,
+    old_size: usize,
+    new_root: &Digest,
+    new_size: usize,
+    scheme: Scheme,
+    proof: &ConsistencyProof,
+) -> bool {
+    if proof.scheme != scheme || old_size == 0 || old_size > new_size {
+        return false;
+    }
+    if old_size == new_size {
+        return proof.nodes.is_empty() && old_root == new_root;
+    }
+    if proof.nodes.is_empty() {
+        return false;
+    }
+
+    let mut path = proof.nodes.iter();
+    // When the old tree is a whole subtree of the new one its root is the start of the path
+    let first = if old_size.is_power_of_two() {
+        *old_root
+    } else {
+        *path.next().unwrap()
+    };
+
+    let mut old_node = old_size - 1;
+    let mut new_node = new_size - 1;
+    while old_node & 1 == 1 {
+        old_node >>= 1;
+        new_node >>= 1;
+    }
+
+    let mut old_hash = first;
+    let mut new_hash = first;
+    for node in path {
+        if new_node == 0 {
+            return false;
+        }
+        if old_node & 1 == 1 || old_node == new_node {
+            old_hash = scheme.hash_nodes::<H>(node, &old_hash);
+            new_hash = scheme.hash_nodes::<H>(node, &new_hash);
+            if old_node & 1 == 0 {
+                while old_node & 1 == 0 && old_node != 0 {
+                    old_node >>= 1;
+                    new_node >>= 1;
+                }
+            }
+        } else {
+            new_hash = scheme.hash_nodes::<H>(&new_hash, node);
+        }
+        old_node >>= 1;
+        new_node >>= 1;
+    }
+    old_hash == *old_root && new_hash == *new_root && new_node == 0
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::test_util::{build_tree_with_scheme, leaves};
+    use crate::Sha256;
+
+    #[test]
+    fn test_consistency_any_sizes() {
+        let leaves = leaves(33);
+        for scheme in [Scheme::Sorted, Scheme::Ordered, Scheme::DomainSeparated] {
+            for new_size in 1..=leaves.len() {
+                let new_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves[..new_size], scheme);
+                for old_size in 1..=new_size {
+                    let old_tree =
+                        MerkleTree::<Sha256>::build_with_scheme(&leaves[..old_size], scheme);
+                    let proof = new_tree.prove_consistency(old_size);
+                    assert!(verify_consistency::<Sha256>(
+                        &old_tree.get_root(),
+                        old_size,
+                        &new_tree.get_root(),
+                        new_size,
+                        scheme,
+                        &proof
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_consistency_proof_size() {
+        // Example from RFC 6962 section 2.1.3: the tree of 7 leaves against the tree of 3
+        let leaves = leaves(7);
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        let proof = merkle_tree.prove_consistency(3);
+        assert_eq!(
+            proof.nodes,
+            Vec::from([
+                merkle_tree.tree[0][2],
+                merkle_tree.tree[0][3],
+                merkle_tree.tree[1][0],
+                merkle_tree.tree[2][1]
+            ])
+        );
+        // Against the tree of 4 the old root is a node of the new tree and is left out
+        let proof = merkle_tree.prove_consistency(4);
+        assert_eq!(proof.nodes, Vec::from([merkle_tree.tree[2][1]]));
+        assert!(merkle_tree.prove_consistency(7).nodes.is_empty());
+    }
+
+    #[test]
+    fn test_consistency_rejects_rewritten_leaves() {
+        let leaves = leaves(10);
+        let old_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves[..6], Scheme::LATEST);
+        let mut new_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        new_tree.update(2, Scheme::LATEST.hash_leaf::<Sha256>(b"rewritten"));
+        let proof = new_tree.prove_consistency(6);
+        assert!(!verify_consistency::<Sha256>(
+            &old_tree.get_root(),
+            6,
+            &new_tree.get_root(),
+            10,
+            Scheme::LATEST,
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_consistency_rejects_wrong_sizes() {
+        let leaves = leaves(10);
+        let old_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves[..6], Scheme::LATEST);
+        let new_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        let proof = new_tree.prove_consistency(6);
+        let (old_root, new_root) = (old_tree.get_root(), new_tree.get_root());
+        assert!(verify_consistency::<Sha256>(
+            &old_root,
+            6,
+            &new_root,
+            10,
+            Scheme::LATEST,
+            &proof
+        ));
+        assert!(!verify_consistency::<Sha256>(
+            &old_root,
+            5,
+            &new_root,
+            10,
+            Scheme::LATEST,
+            &proof
+        ));
+        assert!(!verify_consistency::<Sha256>(
+            &old_root,
+            6,
+            &new_root,
+            17,
+            Scheme::LATEST,
+            &proof
+        ));
+        assert!(!verify_consistency::<Sha256>(
+            &old_root,
+            0,
+            &new_root,
+            10,
+            Scheme::LATEST,
+            &proof
+        ));
+        assert!(!verify_consistency::<Sha256>(
+            &new_root,
+            10,
+            &old_root,
+            6,
+            Scheme::LATEST,
+            &proof
+        ));
+        // Equal sizes need equal roots and no proof
+        let empty = ConsistencyProof {
+            scheme: Scheme::LATEST,
+            nodes: Vec::new(),
+        };
+        assert!(verify_consistency::<Sha256>(
+            &new_root,
+            10,
+            &new_root,
+            10,
+            Scheme::LATEST,
+            &empty
+        ));
+        assert!(!verify_consistency::<Sha256>(
+            &old_root,
+            10,
+            &new_root,
+            10,
+            Scheme::LATEST,
+            &empty
+        ));
+    }
+
+    #[test]
+    fn test_consistency_rejects_other_scheme() {
+        // A valid proof for trees built with another scheme than the verifier recorded
+        let (leaves, new_tree) = build_tree_with_scheme(10, Scheme::Sorted);
+        let old_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves[..6], Scheme::Sorted);
+        let proof = new_tree.prove_consistency(6);
+        let (old_root, new_root) = (old_tree.get_root(), new_tree.get_root());
+        assert!(verify_consistency::<Sha256>(
+            &old_root,
+            6,
+            &new_root,
+            10,
+            Scheme::Sorted,
+            &proof
+        ));
+        assert!(!verify_consistency::<Sha256>(
+            &old_root,
+            6,
+            &new_root,
+            10,
+            Scheme::LATEST,
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_try_prove_consistency() {
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves(5), Scheme::LATEST);
+        for old_size in [0, 6] {
+            assert_eq!(
+                merkle_tree.try_prove_consistency(old_size),
+                Err(MerkleError::OldSizeOutOfRange {
+                    old_size,
+                    leaf_count: 5
+                })
+            );
+        }
+        assert_eq!(
+            merkle_tree.try_prove_consistency(3),
+            Ok(merkle_tree.prove_consistency(3))
+        );
+    }
+}
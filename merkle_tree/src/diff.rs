@@ -0,0 +1,94 @@
+use crate::{MerkleHasher, MerkleTree};
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Return the indices of the leaves which differ between this tree and another, in order
+    ///
+    /// Both trees are walked from the root, descending only into nodes whose hashes differ, so k
+    /// differing leaves take O(k log n) comparisons. Leaves past the end of the smaller tree count as
+    /// differing. Trees built with different schemes share no interior nodes, so every leaf is
+    /// compared.
+    pub fn diff(&self, other: &MerkleTree<H>) -> Vec<usize> {
+        let mut indices = Vec::new();
+        let top_row = self.tree.len().max(other.tree.len()) - 1;
+        self.diff_node(other, top_row, 0, &mut indices);
+        indices
+    }
+
+    /// Add the differing leaves below the node at a row and index to `indices`
+    ///
+    /// The node covers the leaves from `node << row` up to the next node or the end of the tree. Its
+    /// hashes in the two trees can only be compared if it covers the same leaves in both, which is
+    /// not the case for the last nodes of trees of different sizes.
+    fn diff_node(&self, other: &MerkleTree<H>, row: usize, node: usize, indices: &mut Vec<usize>) {
+        let start = node << row;
+        let end = (node + 1) << row;
+        let self_end = end.min(self.num_leaves);
+        let other_end = end.min(other.num_leaves);
+        if start >= self_end.max(other_end) {
+            return;
+        }
+        if self_end == other_end && self.tree[row][node] == other.tree[row][node] {
+            return;
+        }
+        if row == 0 {
+            indices.push(node);
+            return;
+        }
+        self.diff_node(other, row - 1, 2 * node, indices);
+        self.diff_node(other, row - 1, 2 * node + 1, indices);
+    }
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::test_util::leaves;
+    use crate::{Digest, Scheme, Sha256};
+
+    /// Differing leaves found by comparing every leaf
+    fn compare_leaves(a: &[Digest], b: &[Digest]) -> Vec<usize> {
+        (0..a.len().max(b.len()))
+            .filter(|&index| a.get(index) != b.get(index))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_same_size() {
+        for num_leaves in 1..=17 {
+            let leaves = leaves(num_leaves);
+            let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+            assert!(merkle_tree.diff(&merkle_tree).is_empty());
+
+            for changed in [
+                Vec::from([0]),
+                Vec::from([num_leaves - 1]),
+                (0..num_leaves).step_by(3).collect(),
+            ] {
+                let mut other_leaves = leaves.clone();
+                for &index in &changed {
+                    other_leaves[index] = Scheme::LATEST.hash_leaf::<Sha256>(b"changed");
+                }
+                let other = MerkleTree::build_with_scheme(&other_leaves, Scheme::LATEST);
+                assert_eq!(merkle_tree.diff(&other), changed);
+                assert_eq!(other.diff(&merkle_tree), changed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_diff_different_sizes() {
+        for num_leaves in 1..=17 {
+            for other_num_leaves in 1..=17 {
+                let these_leaves = leaves(num_leaves);
+                let mut other_leaves = leaves(other_num_leaves);
+                other_leaves[other_num_leaves / 2] = Scheme::LATEST.hash_leaf::<Sha256>(b"changed");
+                let merkle_tree =
+                    MerkleTree::<Sha256>::build_with_scheme(&these_leaves, Scheme::LATEST);
+                let other = MerkleTree::build_with_scheme(&other_leaves, Scheme::LATEST);
+                let expected = compare_leaves(&these_leaves, &other_leaves);
+                assert_eq!(merkle_tree.diff(&other), expected);
+                assert_eq!(other.diff(&merkle_tree), expected);
+            }
+        }
+    }
+}
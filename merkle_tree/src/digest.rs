@@ -0,0 +1,95 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Number of bytes in a digest
+pub const DIGEST_LEN: usize = 32;
+
+/// A hash digest as raw bytes. Used for every leaf, node, root and proof entry of a tree.
+///
+/// Digests are only converted to hex at the edges, when displayed or serialised.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Digest(pub [u8; DIGEST_LEN]);
+
+impl Digest {
+    pub fn as_bytes(&self) -> &[u8; DIGEST_LEN] {
+        &self.0
+    }
+
+    /// Lowercase hex encoding of the digest
+    pub fn to_hex(&self) -> String {
+        base16ct::lower::encode_string(&self.0)
+    }
+
+    /// Parse a digest from hex. Returns None unless the input is exactly `2 * DIGEST_LEN` hex characters
+    pub fn from_hex(hex: &str) -> Option<Digest> {
+        if hex.len() != 2 * DIGEST_LEN {
+            return None;
+        }
+        let mut bytes = [0u8; DIGEST_LEN];
+        base16ct::mixed::decode(hex, &mut bytes).ok()?;
+        Some(Digest(bytes))
+    }
+
+    /// Write the lowercase hex encoding of the digest into a buffer without allocating
+    pub(crate) fn encode_hex<'a>(&self, buffer: &'a mut [u8; 2 * DIGEST_LEN]) -> &'a [u8] {
+        base16ct::lower::encode(&self.0, buffer).unwrap()
+    }
+}
+
+impl From<[u8; DIGEST_LEN]> for Digest {
+    fn from(bytes: [u8; DIGEST_LEN]) -> Self {
+        Digest(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = [0u8; 2 * DIGEST_LEN];
+        // Lowercase hex is always valid UTF-8
+        f.write_str(core::str::from_utf8(self.encode_hex(&mut buffer)).unwrap())
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Digest({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let hex = "5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9";
+        let digest = Digest::from_hex(hex).unwrap();
+        assert_eq!(digest.as_bytes()[0], 0x5f);
+        assert_eq!(digest.to_hex(), hex);
+        assert_eq!(digest.to_string(), hex);
+        assert_eq!(Digest::from_hex(&hex.to_uppercase()), Some(digest));
+    }
+
+    #[test]
+    fn test_from_hex_rejects_bad_input() {
+        assert_eq!(Digest::from_hex(""), None);
+        assert_eq!(Digest::from_hex("5feceb66"), None);
+        assert_eq!(Digest::from_hex(&"zz".repeat(DIGEST_LEN)), None);
+        assert_eq!(Digest::from_hex(&"00".repeat(DIGEST_LEN + 1)), None);
+    }
+
+    #[test]
+    fn test_ordering_matches_hex_ordering() {
+        let low = Digest([0x0f; DIGEST_LEN]);
+        let high = Digest([0xa0; DIGEST_LEN]);
+        assert!(low < high);
+        assert!(low.to_hex() < high.to_hex());
+    }
+}
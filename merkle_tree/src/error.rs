@@ -0,0 +1,79 @@
+use crate::Scheme;
+use core::fmt;
+
+/// Reasons a tree cannot be built, a proof created or a proof verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkleError {
+    /// A tree was to be built from no leaves
+    NoLeaves,
+    /// An index at or past the last leaf of the tree
+    IndexOutOfRange { index: usize, leaf_count: usize },
+    /// A byte range which is empty or runs past the end of the file. The empty range at the start
+    /// of an empty file is allowed
+    RangeOutOfBounds {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    /// A chunk size of 0, which cannot split a file into chunks
+    InvalidChunkSize,
+    /// A consistency proof against an earlier tree of no leaves or of more leaves than the tree
+    OldSizeOutOfRange { old_size: usize, leaf_count: usize },
+    /// A proof for a tree built with a different hash function than the one verifying it
+    UnexpectedHasher { expected: u8, found: u8 },
+    /// A proof for a tree built with a different scheme than the one the verifier recorded
+    UnexpectedScheme { expected: Scheme, found: Scheme },
+    /// A proof which does not take the leaf to the root
+    InvalidProof,
+    /// A stored tree which is missing, truncated or for another hash function
+    InvalidStoredTree,
+    /// A tree head whose signature is malformed or was not made by the key verifying it
+    InvalidSignature,
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleError::NoLeaves => write!(f, "Number of leaves must be greater than 0"),
+            MerkleError::IndexOutOfRange { index, leaf_count } => write!(
+                f,
+                "Index {} too large. Tree contains {} leaves. Files are 0-indexed.",
+                index, leaf_count
+            ),
+            MerkleError::RangeOutOfBounds { start, end, len } => write!(
+                f,
+                "Byte range {}..{} is empty or not within the file of {} bytes",
+                start, end, len
+            ),
+            MerkleError::InvalidChunkSize => write!(f, "Chunk size must be greater than 0"),
+            MerkleError::OldSizeOutOfRange {
+                old_size,
+                leaf_count,
+            } => write!(
+                f,
+                "Old size {} must be between 1 and the {} leaves in the tree",
+                old_size, leaf_count
+            ),
+            MerkleError::UnexpectedHasher { expected, found } => write!(
+                f,
+                "Proof is for hash function {} but was verified with hash function {}",
+                found, expected
+            ),
+            MerkleError::UnexpectedScheme { expected, found } => write!(
+                f,
+                "Proof is for scheme {:?} but was verified with scheme {:?}",
+                found, expected
+            ),
+            MerkleError::InvalidProof => {
+                write!(f, "Proof does not validate the leaf against the root")
+            }
+            MerkleError::InvalidStoredTree => write!(f, "Stored tree is missing or invalid"),
+            MerkleError::InvalidSignature => {
+                write!(f, "Tree head signature is not valid for the public key")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MerkleError {}
@@ -0,0 +1,131 @@
+use crate::Digest;
+
+/// A hash function with a 32 byte output with which to build Merkle trees
+///
+/// Each implementation is behind a cargo feature of the same name. `Sha256` is enabled by default.
+pub trait MerkleHasher {
+    /// Identifier of the hash function, for recording alongside roots and proofs
+    const ID: u8;
+    /// Human readable name of the hash function
+    const NAME: &'static str;
+
+    /// Hash the concatenation of all parts without first copying them into one buffer
+    fn hash_parts(parts: &[&[u8]]) -> Digest;
+
+    /// Hash a message
+    fn hash(message: &[u8]) -> Digest {
+        Self::hash_parts(&[message])
+    }
+}
+
+/// Implement `MerkleHasher` for a RustCrypto hash function with a 32 byte output
+#[cfg(any(feature = "sha256", feature = "sha512_256", feature = "sha3"))]
+macro_rules! impl_rust_crypto_hasher {
+    ($hasher:ident, $algorithm:ty, $digest_trait:path, $id:expr, $name:expr) => {
+        impl MerkleHasher for $hasher {
+            const ID: u8 = $id;
+            const NAME: &'static str = $name;
+
+            fn hash_parts(parts: &[&[u8]]) -> Digest {
+                use $digest_trait as _;
+                let mut hasher = <$algorithm>::new();
+                for part in parts {
+                    hasher.update(part);
+                }
+                Digest(hasher.finalize().into())
+            }
+        }
+    };
+}
+
+/// SHA-256. The hash function used by every tree before hashers were pluggable
+#[cfg(feature = "sha256")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sha256;
+
+#[cfg(feature = "sha256")]
+impl_rust_crypto_hasher!(Sha256, sha2::Sha256, sha2::Digest, 0, "SHA-256");
+
+/// SHA-512/256. SHA-512 truncated to 32 bytes, which is faster than SHA-256 on 64 bit machines
+#[cfg(feature = "sha512_256")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub struct Sha512_256;
+
+#[cfg(feature = "sha512_256")]
+impl_rust_crypto_hasher!(Sha512_256, sha2::Sha512_256, sha2::Digest, 1, "SHA-512/256");
+
+/// SHA3-256
+#[cfg(feature = "sha3")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub struct Sha3_256;
+
+#[cfg(feature = "sha3")]
+impl_rust_crypto_hasher!(Sha3_256, sha3::Sha3_256, sha3::Digest, 2, "SHA3-256");
+
+/// BLAKE3 with its default 32 byte output
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blake3;
+
+#[cfg(feature = "blake3")]
+impl MerkleHasher for Blake3 {
+    const ID: u8 = 3;
+    const NAME: &'static str = "BLAKE3";
+
+    fn hash_parts(parts: &[&[u8]]) -> Digest {
+        let mut hasher = blake3::Hasher::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        Digest(hasher.finalize().into())
+    }
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "sha256",
+        feature = "sha512_256",
+        feature = "sha3",
+        feature = "blake3"
+    )
+))]
+mod tests {
+    use super::*;
+
+    /// Check the hasher against a known answer for "abc" and that hashing in parts matches
+    fn check_hasher<H: MerkleHasher>(expected_abc: &str) {
+        assert_eq!(H::hash(b"abc").to_hex(), expected_abc);
+        assert_eq!(H::hash_parts(&[b"a", b"", b"bc"]), H::hash(b"abc"));
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn test_sha256() {
+        check_hasher::<Sha256>("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[cfg(feature = "sha512_256")]
+    #[test]
+    fn test_sha512_256() {
+        check_hasher::<Sha512_256>(
+            "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23",
+        );
+    }
+
+    #[cfg(feature = "sha3")]
+    #[test]
+    fn test_sha3_256() {
+        check_hasher::<Sha3_256>(
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+        );
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_blake3() {
+        check_hasher::<Blake3>("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+    }
+}
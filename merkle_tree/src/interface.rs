@@ -1,12 +1,67 @@
+use crate::{
+    ConsistencyProof, Digest, FileRangeProof, LeafUpdate, Proof, Salt, Scheme, SignedTreeHead,
+};
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
+use std::fmt;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct StoreRequest {
     pub files: Vec<String>,
-    pub hashes: Vec<String>,
+    pub hashes: Vec<Digest>,
+    /// Scheme with which to build the Merkle tree. Requests without one use the legacy `Scheme::Sorted`
+    #[serde(default)]
+    pub scheme: Scheme,
+    /// Chunk size if each hash is the leaf of a `ChunkTree` over the file, which lets ranges of the
+    /// file be fetched. Requests without one hash each file whole with the scheme
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
+    /// Salt of each file if each hash is salted with `salt_leaf`. The server returns a file's salt
+    /// only with the file, so proofs for other files do not let its contents be guessed
+    #[serde(default)]
+    pub salts: Option<Vec<Salt>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct StoreResponse {
-    pub root: String,
+    pub root: Digest,
+    /// Server's signed commitment to the root, which the client keeps as a receipt
+    pub signed_tree_head: SignedTreeHead,
+}
+
+/// Files to add to the end of the stored collection, which keeps the scheme it was stored with
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AppendRequest {
+    pub files: Vec<String>,
+    pub hashes: Vec<Digest>,
+    /// Salt of each file, which must be sent if and only if the collection was stored salted
+    #[serde(default)]
+    pub salts: Option<Vec<Salt>>,
+}
+
+/// New root after an append, with a proof that the tree before the append is a prefix of it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AppendResponse {
+    pub root: Digest,
+    pub consistency_proof: ConsistencyProof,
+    pub signed_tree_head: SignedTreeHead,
+}
+
+/// Replacement for the stored file at an index
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateRequest {
+    pub file_index: usize,
+    pub file: String,
+    pub hash: Digest,
+    /// New salt for the file, which must be sent if and only if the collection was stored salted
+    #[serde(default)]
+    pub salt: Option<Salt>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateResponse {
+    pub update: LeafUpdate,
+    pub signed_tree_head: SignedTreeHead,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -17,5 +72,152 @@ pub struct FetchRequest {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FetchResponse {
     pub file: String,
-    pub proof: Vec<String>,
+    pub proof: Proof,
+    /// Salt of the file's leaf, if the collection is salted
+    #[serde(default)]
+    pub salt: Option<Salt>,
+    /// Head the server signed when the stored files last changed
+    pub signed_tree_head: SignedTreeHead,
+}
+
+/// Bytes `start..end` of the stored file at an index
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FetchRangeRequest {
+    pub file_index: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The whole chunks holding the requested bytes, which may start before and end after them
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FetchRangeResponse {
+    #[serde(with = "hex_bytes")]
+    pub data: Vec<u8>,
+    pub proof: FileRangeProof,
+    pub signed_tree_head: SignedTreeHead,
+}
+
+/// Raw bytes, which need not be valid UTF-8, are sent as lowercase hex strings
+pub(crate) mod hex_bytes {
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base16ct::lower::encode_string(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        base16ct::mixed::decode_vec(&hex)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(&hex), &"hex encoded bytes"))
+    }
+}
+
+/// Digests are sent and stored as lowercase hex strings
+impl serde::Serialize for Digest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Digest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(DigestVisitor)
+    }
+}
+
+/// Salts are sent and stored as lowercase hex strings, as digests are
+impl serde::Serialize for Salt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Salt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex: String = serde::Deserialize::deserialize(deserializer)?;
+        Salt::from_hex(&hex).ok_or_else(|| {
+            de::Error::invalid_value(de::Unexpected::Str(&hex), &"a hex encoded 32 byte salt")
+        })
+    }
+}
+
+struct DigestVisitor;
+
+impl Visitor<'_> for DigestVisitor {
+    type Value = Digest;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a hex encoded {} byte digest", crate::DIGEST_LEN)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Digest, E> {
+        Digest::from_hex(value).ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TreeHead;
+
+    #[test]
+    fn test_digest_is_serialised_as_hex() {
+        let hex = "5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9";
+        let digest = Digest::from_hex(hex).unwrap();
+        let json = serde_json::to_string(&digest).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex));
+        let parsed: Digest = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, digest);
+    }
+
+    #[test]
+    fn test_range_data_is_serialised_as_hex() {
+        let data = Vec::from([0x00, 0xff, 0x10]);
+        let mut json = Vec::new();
+        hex_bytes::serialize(&data, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        assert_eq!(json, b"\"00ff10\"");
+        let mut deserializer = serde_json::Deserializer::from_slice(&json);
+        assert_eq!(hex_bytes::deserialize(&mut deserializer).unwrap(), data);
+        let mut deserializer = serde_json::Deserializer::from_str("\"0g\"");
+        assert!(hex_bytes::deserialize(&mut deserializer).is_err());
+    }
+
+    #[test]
+    fn test_salts_default_to_none() {
+        let json = "{\"files\":[\"a\"],\"hashes\":[]}";
+        let append_request: AppendRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(append_request.salts, None);
+
+        let salt = Salt([0xab; crate::SALT_LEN]);
+        let json = serde_json::to_string(&salt).unwrap();
+        assert_eq!(json, format!("\"{}\"", "ab".repeat(crate::SALT_LEN)));
+        assert_eq!(serde_json::from_str::<Salt>(&json).unwrap(), salt);
+        assert!(serde_json::from_str::<Salt>("\"abab\"").is_err());
+    }
+
+    #[test]
+    fn test_invalid_digest_is_rejected() {
+        let root = Digest([1; 32]);
+        let store_response = StoreResponse {
+            root,
+            signed_tree_head: SignedTreeHead {
+                tree_head: TreeHead {
+                    root,
+                    leaf_count: 1,
+                    scheme: Scheme::LATEST,
+                    hash_id: 0,
+                    timestamp: 1_700_000_000,
+                },
+                signature: vec![0; 64],
+            },
+        };
+        // The response is valid until only its root is replaced
+        let mut json = serde_json::to_value(&store_response).unwrap();
+        assert!(serde_json::from_value::<StoreResponse>(json.clone()).is_ok());
+        json["root"] = "5feceb66".into();
+        assert!(serde_json::from_value::<StoreResponse>(json.clone()).is_err());
+        json["root"] = 7.into();
+        assert!(serde_json::from_value::<StoreResponse>(json).is_err());
+    }
 }
@@ -1,13 +1,90 @@
+//! Merkle trees over file hashes, with proofs of inclusion
+//!
+//! Without the default `std` feature the crate builds with `no_std` and `alloc`. `verify-only` then
+//! provides only `Proof` decoding and verification, the free `verify` functions, chunked file and
+//! salted leaves, `Scheme` and the hash functions. Building trees and everything the server uses needs `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(feature = "std")]
 extern crate serde;
 
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub mod chunk;
+#[cfg(feature = "std")]
+pub mod consistency;
+#[cfg(feature = "std")]
+pub mod diff;
+pub mod digest;
+pub mod error;
+pub mod hasher;
+#[cfg(feature = "std")]
 pub mod interface;
+#[cfg(feature = "std")]
+pub mod mmr;
+#[cfg(all(feature = "std", feature = "sha256"))]
+pub mod multi_hash;
+#[cfg(feature = "std")]
+pub mod multiproof;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub mod proof;
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub mod root_builder;
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub mod salt;
+#[cfg(feature = "std")]
+pub mod sparse;
+#[cfg(feature = "std")]
+pub mod stored;
+#[cfg(feature = "std")]
+pub mod tree_head;
+#[cfg(feature = "std")]
+pub mod update;
+
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub use chunk::file_leaf;
+#[cfg(feature = "std")]
+pub use chunk::{file_leaf_from_reader, ChunkRangeProof, ChunkTree, FileRangeProof};
+#[cfg(feature = "std")]
+pub use consistency::{verify_consistency, ConsistencyProof};
+pub use digest::{Digest, DIGEST_LEN};
+pub use error::MerkleError;
+pub use hasher::MerkleHasher;
+#[cfg(feature = "sha256")]
+pub use hasher::Sha256;
+#[cfg(feature = "std")]
+pub use mmr::{verify_mmr, MerkleMountainRange, MmrProof};
+#[cfg(all(feature = "std", feature = "sha256"))]
+pub use multi_hash::{MultiHashProof, MultiHashRoot, MultiHashTree};
+#[cfg(feature = "std")]
+pub use multiproof::{verify_many, MultiProof};
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub use proof::Proof;
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub use root_builder::MerkleRootBuilder;
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub use salt::{salt_leaf, Salt, SALT_LEN};
+#[cfg(feature = "std")]
+pub use sparse::{verify_membership, verify_non_membership, SparseMerkleTree, SparseProof};
+#[cfg(feature = "std")]
+pub use stored::{prove_from_stored, StoredTreeHeader, STORED_HEADER_LEN};
+#[cfg(feature = "std")]
+pub use tree_head::{SignedTreeHead, TreeHead};
+#[cfg(feature = "std")]
+pub use update::{verify_update, LeafUpdate};
 
-use sha2::{Digest, Sha256};
+#[cfg(feature = "std")]
+use std::marker::PhantomData;
 
+/// Merkle tree over leaf hashes, with nodes hashed by the hash function `H`
+#[cfg(feature = "std")]
 #[derive(Debug)]
-pub struct MerkleTree {
+pub struct MerkleTree<H: MerkleHasher> {
     /// Binary tree represented as a 2-dimensional vector in which the outer vector represents each row and inner vector represents the nodes on that row   
     /// Note that leaf nodes are stored in row 0 and the root node in row (tree.len()-1).
     ///
@@ -18,126 +95,451 @@ pub struct MerkleTree {
     /// row 1:      NODE      NODE  
     ///                 \    /  
     /// row 2:           ROOT   
-    pub tree: Vec<Vec<String>>,
+    pub tree: Vec<Vec<Digest>>,
     pub num_leaves: usize,
+    pub scheme: Scheme,
+    pub hasher: PhantomData<H>,
+}
+
+/// Rule used to combine a pair of sibling nodes into their parent node
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scheme {
+    /// Sibling hashes are sorted before being concatenated. A proof then only shows that a leaf is
+    /// somewhere in the tree, not at which index. Kept for roots created before `Ordered` existed.
+    #[default]
+    Sorted,
+    /// The left sibling is always concatenated first, so a proof binds a leaf to its index
+    Ordered,
+    /// As `Ordered`, but leaves and nodes are hashed with distinct prefixes as in RFC 6962:
+    /// `hash(0x00 || data)` for a leaf and `hash(0x01 || left || right)` over the raw digests for a
+    /// node. An interior node can then never be passed off as a leaf.
+    DomainSeparated,
+}
+
+/// Prefix for data hashed into a leaf under `Scheme::DomainSeparated`
+const LEAF_PREFIX: u8 = 0x00;
+/// Prefix for a pair of nodes hashed into their parent under `Scheme::DomainSeparated`
+const NODE_PREFIX: u8 = 0x01;
+
+impl Scheme {
+    /// Scheme used for newly built trees
+    pub const LATEST: Scheme = Scheme::DomainSeparated;
+
+    /// Version number of the scheme. Versions only ever increase as schemes are added
+    pub fn version(&self) -> u8 {
+        match self {
+            Scheme::Sorted => 0,
+            Scheme::Ordered => 1,
+            Scheme::DomainSeparated => 2,
+        }
+    }
+
+    /// Return the scheme with the given version number, if there is one
+    pub fn from_version(version: u8) -> Option<Scheme> {
+        match version {
+            0 => Some(Scheme::Sorted),
+            1 => Some(Scheme::Ordered),
+            2 => Some(Scheme::DomainSeparated),
+            _ => None,
+        }
+    }
+
+    /// Hash the contents of a file into a leaf
+    pub fn hash_leaf<H: MerkleHasher>(&self, data: &[u8]) -> Digest {
+        match self {
+            Scheme::Sorted | Scheme::Ordered => H::hash(data),
+            Scheme::DomainSeparated => H::hash_parts(&[&[LEAF_PREFIX], data]),
+        }
+    }
+
+    /// Hash the contents of each file into a leaf
+    ///
+    /// With the `parallel` feature large collections are hashed across threads.
+    #[cfg(feature = "std")]
+    pub fn hash_leaves<H: MerkleHasher, T: AsRef<[u8]> + Sync>(&self, data: &[T]) -> Vec<Digest> {
+        #[cfg(feature = "parallel")]
+        if data.len() >= parallel::MIN_PARALLEL_LEN {
+            return parallel::hash_leaves::<H, T>(self, data);
+        }
+        data.iter()
+            .map(|item| self.hash_leaf::<H>(item.as_ref()))
+            .collect()
+    }
+
+    /// Hash a left and right sibling pair into their parent node
+    ///
+    /// `Sorted` and `Ordered` hash the hex text of the children so that roots created before
+    /// digests were stored as bytes still verify.
+    pub fn hash_nodes<H: MerkleHasher>(&self, left: &Digest, right: &Digest) -> Digest {
+        match self {
+            Scheme::Sorted => {
+                if left > right {
+                    hash_hex_concat::<H>(left, right)
+                } else {
+                    hash_hex_concat::<H>(right, left)
+                }
+            }
+            Scheme::Ordered => hash_hex_concat::<H>(left, right),
+            Scheme::DomainSeparated => {
+                H::hash_parts(&[&[NODE_PREFIX], left.as_bytes(), right.as_bytes()])
+            }
+        }
+    }
+
+    /// Hash pairs of nodes on a row of a tree to build the row above it
+    ///
+    /// With the `parallel` feature large rows are hashed across threads.
+    #[cfg(feature = "std")]
+    fn hash_row<H: MerkleHasher>(&self, row: &[Digest]) -> Vec<Digest> {
+        #[cfg(feature = "parallel")]
+        if row.len() >= parallel::MIN_PARALLEL_LEN {
+            return parallel::hash_row::<H>(self, row);
+        }
+        // Hash concaternation of pairs of items on current row to build next row
+        row.chunks(2)
+            .map(|pair| self.hash_pair::<H>(pair))
+            .collect()
+    }
+
+    /// Hash a chunk of a row into its parent. An unpaired last node is promoted to the next row
+    #[cfg(feature = "std")]
+    fn hash_pair<H: MerkleHasher>(&self, pair: &[Digest]) -> Digest {
+        match pair {
+            [left, right] => self.hash_nodes::<H>(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Take a root hash, leaf hash, the leaf's index, the number of leaves in the tree and a proof
+    /// and return true if the proof validates the leaf at exactly that index
+    ///
+    /// The side each proof hash sits on is implied by the index and number of leaves. Under
+    /// `Scheme::Sorted` the order is ignored when hashing, so only the shape of the proof is checked.
+    #[cfg(any(feature = "std", feature = "verify-only"))]
+    pub fn verify_at_index<H: MerkleHasher>(
+        &self,
+        root_hash: &Digest,
+        leaf_hash: &Digest,
+        index: usize,
+        leaf_count: usize,
+        proof: &[Digest],
+    ) -> bool {
+        if index >= leaf_count {
+            return false;
+        }
+        let mut proof = proof.iter();
+        let mut current_hash = *leaf_hash;
+        let mut node = index;
+        let mut row_len = leaf_count;
+        while row_len > 1 {
+            let sibling = find_node_sibling(node);
+            // A node without a sibling is promoted to the next row unchanged
+            if sibling < row_len {
+                let sibling_hash = match proof.next() {
+                    Some(sibling_hash) => sibling_hash,
+                    None => return false,
+                };
+                current_hash = if node < sibling {
+                    self.hash_nodes::<H>(&current_hash, sibling_hash)
+                } else {
+                    self.hash_nodes::<H>(sibling_hash, &current_hash)
+                };
+            }
+            node = find_parent_of_node(node);
+            row_len = row_len.div_ceil(2);
+        }
+        proof.next().is_none() && current_hash == *root_hash
+    }
 }
 
-impl MerkleTree {
+#[cfg(feature = "std")]
+impl<H: MerkleHasher> MerkleTree<H> {
     /// Take a list of leaf hashes and build full merkle tree
-    pub fn build(leaves: &Vec<String>) -> MerkleTree {
-        if !leaves.len().is_power_of_two() {
-            panic!("Number of leaves must be power of 2");
+    ///
+    /// Any non-zero number of leaves is accepted. Where a row has an odd number of nodes the last
+    /// node has no sibling and is promoted unchanged to the row above. The resulting tree has the
+    /// same shape as the RFC 6962 tree which splits its leaves at the largest power of 2.
+    ///
+    /// For example, with 3 leaves:
+    ///
+    /// row 0:  LEAF  LEAF  LEAF
+    ///            \  /       |
+    /// row 1:      NODE      LEAF
+    ///                 \    /
+    /// row 2:           ROOT
+    ///
+    /// Nodes are combined with the legacy `Scheme::Sorted`, which is kept only so that roots built
+    /// before schemes existed still verify. New trees should use
+    /// `build_with_scheme(leaves, Scheme::LATEST)`, and keep the scheme alongside the root.
+    #[deprecated(
+        note = "builds with the legacy Scheme::Sorted. Use build_with_scheme(leaves, Scheme::LATEST)"
+    )]
+    pub fn build(leaves: &[Digest]) -> MerkleTree<H> {
+        MerkleTree::build_with_scheme(leaves, Scheme::Sorted)
+    }
+
+    /// Take a list of leaf hashes and build full merkle tree, combining nodes with the given scheme
+    ///
+    /// Panics if there are no leaves. See `try_build_with_scheme`.
+    pub fn build_with_scheme(leaves: &[Digest], scheme: Scheme) -> MerkleTree<H> {
+        MerkleTree::try_build_with_scheme(leaves, scheme)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `build`, but returns an error rather than panicking if there are no leaves
+    #[deprecated(
+        note = "builds with the legacy Scheme::Sorted. Use try_build_with_scheme(leaves, Scheme::LATEST)"
+    )]
+    pub fn try_build(leaves: &[Digest]) -> Result<MerkleTree<H>, MerkleError> {
+        MerkleTree::try_build_with_scheme(leaves, Scheme::Sorted)
+    }
+
+    /// As `build_with_scheme`, but returns an error rather than panicking if there are no leaves
+    pub fn try_build_with_scheme(
+        leaves: &[Digest],
+        scheme: Scheme,
+    ) -> Result<MerkleTree<H>, MerkleError> {
+        if leaves.is_empty() {
+            return Err(MerkleError::NoLeaves);
         }
-        let depth: usize = MerkleTree::find_depth(leaves.len());
+        let depth: usize = find_depth(leaves.len());
 
         // Row 0
-        let mut tree: Vec<Vec<String>> = Vec::with_capacity(depth);
-        tree.push(leaves.clone());
+        let mut tree: Vec<Vec<Digest>> = Vec::with_capacity(depth);
+        tree.push(leaves.to_vec());
 
         // Build each row of Merkle tree
         for row in 0..depth - 1 {
-            let mut next_row: Vec<String> = vec![];
-            // Hash concaternation of pairs of items on current row to build next row
-            for i in (0..tree[row].len()).step_by(2) {
-                let concat = concat_string(&tree[row][i], &tree[row][i + 1]);
-                next_row.push(hash(concat.as_ref()));
-            }
-            // println!("next row: {:?}", next_row);
+            let next_row = scheme.hash_row::<H>(&tree[row]);
             tree.push(next_row);
         }
 
-        MerkleTree {
+        Ok(MerkleTree {
             tree,
             num_leaves: leaves.len(),
+            scheme,
+            hasher: PhantomData,
+        })
+    }
+
+    /// Append a leaf to the tree and return the new root
+    ///
+    /// Only the nodes on the path from the new leaf to the root are recomputed, adding a row on top
+    /// when the tree outgrows its depth.
+    pub fn push(&mut self, leaf: Digest) -> Digest {
+        self.tree[0].push(leaf);
+        self.num_leaves += 1;
+        self.rehash_path(self.num_leaves - 1);
+        self.get_root()
+    }
+
+    /// Append each of the leaves to the tree in order and return the new root
+    pub fn extend(&mut self, leaves: &[Digest]) -> Digest {
+        for leaf in leaves {
+            self.push(*leaf);
         }
+        self.get_root()
     }
 
-    pub fn get_root(&self) -> String {
-        self.tree[self.tree.len() - 1][0].clone()
+    /// Recompute each node on the path from a leaf to the root
+    fn rehash_path(&mut self, leaf_index: usize) {
+        let mut node = leaf_index;
+        let mut row = 0;
+        while self.tree[row].len() > 1 {
+            let sibling = find_node_sibling(node);
+            let parent_hash = if sibling < self.tree[row].len() {
+                let (left, right) = (node.min(sibling), node.max(sibling));
+                self.scheme
+                    .hash_nodes::<H>(&self.tree[row][left], &self.tree[row][right])
+            } else {
+                // Unpaired last node is promoted to the next row
+                self.tree[row][node]
+            };
+
+            let parent = find_parent_of_node(node);
+            if row + 1 == self.tree.len() {
+                self.tree.push(Vec::with_capacity(1));
+            }
+            let next_row = &mut self.tree[row + 1];
+            if parent < next_row.len() {
+                next_row[parent] = parent_hash;
+            } else {
+                next_row.push(parent_hash);
+            }
+            node = parent;
+            row += 1;
+        }
+    }
+
+    pub fn get_root(&self) -> Digest {
+        self.tree[self.tree.len() - 1][0]
     }
 
     /// Return index for item in each row of the tree from a particular leaf to root
     pub fn find_path_leaf_to_root(&self, leaf_index: usize) -> Vec<usize> {
         let mut path = Vec::with_capacity(self.tree.len());
         path.push(leaf_index);
-        for row in 0..self.tree.len() - 2 {
-            path.push(MerkleTree::find_parent_of_node(path[row]))
+        for row in 0..self.tree.len().saturating_sub(2) {
+            path.push(find_parent_of_node(path[row]))
         }
         path
     }
 
-    /// The parent index of a child node is the quotient of the child index when divided by 2
-    pub fn find_parent_of_node(index: usize) -> usize {
-        index.div_euclid(2)
-    }
-
-    /// The sibling node index of a given node is:
-    ///  If node index is even: the previous in the row
-    ///  If node index is odd: the next in the row
-    pub fn find_node_sibling(index: usize) -> usize {
-        if index % 2 == 0 {
-            return index + 1;
-        }
-        return index - 1;
+    /// Create a vector of hashes which are the nodes required to rebuild the root hash from the queried index
+    ///
+    /// Rows in which the node on the path has no sibling (it was promoted) contribute nothing, so
+    /// the proof may be shorter than the depth of the tree.
+    ///
+    /// Panics if the index is out of range. See `try_prove`.
+    pub fn prove(&self, index: usize) -> Vec<Digest> {
+        self.try_prove(index)
+            .unwrap_or_else(|error| panic!("{}", error))
     }
 
-    /// Create a vector of hashes which are the nodes required to rebuild the root hash from the queried index
-    pub fn prove(&self, index: usize) -> Vec<String> {
-        if index > self.num_leaves - 1 {
-            panic!(
-                "Index too large. Tree contains {} leaves. Files are 0-indexed.",
-                self.num_leaves
-            )
-        }
+    /// As `prove`, but returns an error rather than panicking if the index is out of range
+    pub fn try_prove(&self, index: usize) -> Result<Vec<Digest>, MerkleError> {
+        self.check_index(index)?;
         // First find the indicies of each node in path from leaf to root
         let path_to_root = self.find_path_leaf_to_root(index);
 
-        // Next find the sibling node to each node in the path to root
-        let sibling_path: Vec<usize> = path_to_root
-            .into_iter()
-            .map(|x| MerkleTree::find_node_sibling(x))
-            .collect();
-
-        // The proof vector then is a hash from each row at index in sibling_path vector
-        let mut proof = Vec::new();
-        for row in 0..self.tree.len() - 1 {
-            proof.push(self.tree[row][sibling_path[row]].clone())
+        // The proof vector then is the hash of the sibling of each node in the path, where one exists
+        let mut proof = Vec::with_capacity(path_to_root.len());
+        for (row, node) in path_to_root.into_iter().enumerate() {
+            let sibling = find_node_sibling(node);
+            if sibling < self.tree[row].len() {
+                proof.push(self.tree[row][sibling])
+            }
         }
-        proof
+        Ok(proof)
     }
 
-    fn find_depth(num_items: usize) -> usize {
-        (num_items.next_power_of_two().ilog2() + 1)
-            .try_into()
-            .unwrap()
+    /// Return an error unless the index is that of a leaf
+    fn check_index(&self, index: usize) -> Result<(), MerkleError> {
+        if index >= self.num_leaves {
+            return Err(MerkleError::IndexOutOfRange {
+                index,
+                leaf_count: self.num_leaves,
+            });
+        }
+        Ok(())
     }
 }
 
-/// Sha256 hash a message. Return as hex string
-pub fn hash(message: &[u8]) -> String {
-    let hash = Sha256::digest(message);
-    base16ct::lower::encode_string(&hash)
+/// The parent index of a child node is the quotient of the child index when divided by 2
+pub fn find_parent_of_node(index: usize) -> usize {
+    index.div_euclid(2)
 }
 
-/// Take two Strings and copy them into a new String which is a concaternation of them  
-/// Strings are ordered lexigraphically
-fn concat_string(string1: &String, string2: &String) -> String {
-    if string1 > string2 {
-        return string1.clone() + &string2;
+/// The sibling node index of a given node is:
+///  If node index is even: the next in the row
+///  If node index is odd: the previous in the row
+pub fn find_node_sibling(index: usize) -> usize {
+    if index.is_multiple_of(2) {
+        return index + 1;
     }
-    string2.clone() + &string1
+    index - 1
+}
+
+/// Number of rows in a tree with the given number of leaves, including the leaf and root rows
+#[cfg(feature = "std")]
+fn find_depth(num_items: usize) -> usize {
+    (num_items.next_power_of_two().ilog2() + 1)
+        .try_into()
+        .unwrap()
+}
+
+/// Sha256 hash a message
+#[cfg(feature = "sha256")]
+pub fn hash(message: &[u8]) -> Digest {
+    Sha256::hash(message)
+}
+
+/// Hash the concatenation of the hex text of two digests, as the `Sorted` and `Ordered` schemes do
+fn hash_hex_concat<H: MerkleHasher>(first: &Digest, second: &Digest) -> Digest {
+    let mut first_hex = [0u8; 2 * DIGEST_LEN];
+    let mut second_hex = [0u8; 2 * DIGEST_LEN];
+    H::hash_parts(&[
+        first.encode_hex(&mut first_hex),
+        second.encode_hex(&mut second_hex),
+    ])
 }
 
 /// Take a root hash, item hash and proof and return true if proof validates the item hash in merkle tree with given root
-pub fn verify(root_hash: &String, item_hash: &String, proof: &Vec<String>) -> bool {
-    let mut current_hash = item_hash.clone();
-    for i in 0..proof.len() {
-        current_hash = hash(concat_string(&current_hash, &proof[i].clone()).as_ref());
+///
+/// This is for trees built with `Scheme::Sorted` and does not check which index the item is at.
+/// Prefer `verify_at_index` for trees built with `Scheme::Ordered`.
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub fn verify<H: MerkleHasher>(root_hash: &Digest, item_hash: &Digest, proof: &[Digest]) -> bool {
+    try_verify::<H>(root_hash, item_hash, proof).is_ok()
+}
+
+/// As `verify`, but returns `MerkleError::InvalidProof` rather than false
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub fn try_verify<H: MerkleHasher>(
+    root_hash: &Digest,
+    item_hash: &Digest,
+    proof: &[Digest],
+) -> Result<(), MerkleError> {
+    let mut current_hash = *item_hash;
+    for sibling in proof {
+        current_hash = Scheme::Sorted.hash_nodes::<H>(&current_hash, sibling);
+    }
+    if current_hash != *root_hash {
+        return Err(MerkleError::InvalidProof);
+    }
+    Ok(())
+}
+
+/// Take a root hash, leaf hash, the leaf's index, the number of leaves and a proof and return true
+/// if the proof validates the leaf at that index of a tree built with `Scheme::Ordered`
+#[cfg(any(feature = "std", feature = "verify-only"))]
+pub fn verify_at_index<H: MerkleHasher>(
+    root_hash: &Digest,
+    leaf_hash: &Digest,
+    index: usize,
+    leaf_count: usize,
+    proof: &[Digest],
+) -> bool {
+    Scheme::Ordered.verify_at_index::<H>(root_hash, leaf_hash, index, leaf_count, proof)
+}
+
+/// Trees and leaves shared by the tests of each module
+#[cfg(all(test, feature = "std", feature = "sha256"))]
+pub(crate) mod test_util {
+    use crate::{Digest, MerkleTree, Scheme, Sha256};
+
+    /// Leaves hashed with `scheme` from the numbers 0 to `num_leaves - 1`
+    fn leaves_with_scheme(num_leaves: usize, scheme: Scheme) -> Vec<Digest> {
+        (0..num_leaves)
+            .map(|x| scheme.hash_leaf::<Sha256>(x.to_string().as_ref()))
+            .collect()
+    }
+
+    /// Leaves hashed with `Scheme::LATEST` from the numbers 0 to `num_leaves - 1`
+    pub(crate) fn leaves(num_leaves: usize) -> Vec<Digest> {
+        leaves_with_scheme(num_leaves, Scheme::LATEST)
+    }
+
+    /// Tree built with `scheme` throughout, and its leaves. Tests use another scheme than
+    /// `Scheme::LATEST` to check that proofs from it are rejected
+    pub(crate) fn build_tree_with_scheme(
+        num_leaves: usize,
+        scheme: Scheme,
+    ) -> (Vec<Digest>, MerkleTree<Sha256>) {
+        let leaves = leaves_with_scheme(num_leaves, scheme);
+        let merkle_tree = MerkleTree::build_with_scheme(&leaves, scheme);
+        (leaves, merkle_tree)
+    }
+
+    /// Tree built with `Scheme::LATEST`, and its leaves
+    pub(crate) fn build_tree(num_leaves: usize) -> (Vec<Digest>, MerkleTree<Sha256>) {
+        build_tree_with_scheme(num_leaves, Scheme::LATEST)
     }
-    current_hash == *root_hash
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std", feature = "sha256"))]
 mod tests {
     use super::*;
 
@@ -149,49 +551,61 @@ mod tests {
             String::from("2"),
             String::from("3"),
         ];
-        let hashes: Vec<String> = inputs.into_iter().map(|x| hash(x.as_ref())).collect();
-        let merkle_tree = MerkleTree::build(&hashes);
+        let hashes: Vec<Digest> = inputs.into_iter().map(|x| hash(x.as_ref())).collect();
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Sorted);
         // Data generated manually
         assert_eq!(
-            merkle_tree.tree[0][0],
-            String::from("5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9")
+            merkle_tree.tree[0][0].to_hex(),
+            "5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9"
         );
         assert_eq!(
-            merkle_tree.tree[0][1],
-            String::from("6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b")
+            merkle_tree.tree[0][1].to_hex(),
+            "6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b"
         );
         assert_eq!(
-            merkle_tree.tree[0][2],
-            String::from("d4735e3a265e16eee03f59718b9b5d03019c07d8b6c51f90da3a666eec13ab35")
+            merkle_tree.tree[0][2].to_hex(),
+            "d4735e3a265e16eee03f59718b9b5d03019c07d8b6c51f90da3a666eec13ab35"
         );
         assert_eq!(
-            merkle_tree.tree[0][3],
-            String::from("4e07408562bedb8b60ce05c1decfe3ad16b72230967de01f640b7e4729b49fce")
+            merkle_tree.tree[0][3].to_hex(),
+            "4e07408562bedb8b60ce05c1decfe3ad16b72230967de01f640b7e4729b49fce"
         );
         assert_eq!(
-            merkle_tree.tree[1][0],
-            String::from("dbdbf4fb684471f421fb255100e433c77fd1aac71c7a3739e9897168aec67ec1")
+            merkle_tree.tree[1][0].to_hex(),
+            "dbdbf4fb684471f421fb255100e433c77fd1aac71c7a3739e9897168aec67ec1"
         );
         assert_eq!(
-            merkle_tree.tree[1][1],
-            String::from("70311d9d203b2d7e4ff70d7fce219f82a4fcf73a110dc80187dfefb7c6e4bb87")
+            merkle_tree.tree[1][1].to_hex(),
+            "70311d9d203b2d7e4ff70d7fce219f82a4fcf73a110dc80187dfefb7c6e4bb87"
         );
         assert_eq!(
-            merkle_tree.tree[2][0],
-            String::from("a48572b1744e5e3f3473c9eaa91f73be774712d2b207c34eb537023af0ec6528")
+            merkle_tree.tree[2][0].to_hex(),
+            "a48572b1744e5e3f3473c9eaa91f73be774712d2b207c34eb537023af0ec6528"
         );
 
         let root_hash = merkle_tree.get_root();
 
         // Build all proof and verify
         let mut proof = merkle_tree.prove(0usize);
-        assert!(verify(&root_hash, &hashes[0], &proof));
+        assert!(verify::<Sha256>(&root_hash, &hashes[0], &proof));
         proof = merkle_tree.prove(1usize);
-        assert!(verify(&root_hash, &hashes[1], &proof));
+        assert!(verify::<Sha256>(&root_hash, &hashes[1], &proof));
         proof = merkle_tree.prove(2usize);
-        assert!(verify(&root_hash, &hashes[2], &proof));
+        assert!(verify::<Sha256>(&root_hash, &hashes[2], &proof));
         proof = merkle_tree.prove(3usize);
-        assert!(verify(&root_hash, &hashes[3], &proof));
+        assert!(verify::<Sha256>(&root_hash, &hashes[3], &proof));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_build_uses_legacy_scheme() {
+        let hashes: Vec<Digest> = (0..5).map(|x| hash(x.to_string().as_ref())).collect();
+        let merkle_tree = MerkleTree::<Sha256>::build(&hashes);
+        assert_eq!(merkle_tree.scheme, Scheme::Sorted);
+        assert_eq!(
+            merkle_tree.get_root(),
+            MerkleTree::<Sha256>::try_build(&hashes).unwrap().get_root()
+        );
     }
 
     #[test]
@@ -202,50 +616,114 @@ mod tests {
             String::from("2"),
             String::from("3"),
         ];
-        let hashes: Vec<String> = inputs.into_iter().map(|x| hash(x.as_ref())).collect();
-        let merkle_tree = MerkleTree::build(&hashes);
+        let hashes: Vec<Digest> = inputs.into_iter().map(|x| hash(x.as_ref())).collect();
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Sorted);
         assert_eq!(
             merkle_tree.prove(0),
-            Vec::from([
-                merkle_tree.tree[0][1].clone(),
-                merkle_tree.tree[1][1].clone()
-            ])
+            Vec::from([merkle_tree.tree[0][1], merkle_tree.tree[1][1]])
         );
         assert_eq!(
             merkle_tree.prove(1),
-            Vec::from([
-                merkle_tree.tree[0][0].clone(),
-                merkle_tree.tree[1][1].clone()
-            ])
+            Vec::from([merkle_tree.tree[0][0], merkle_tree.tree[1][1]])
         );
         assert_eq!(
             merkle_tree.prove(2),
-            Vec::from([
-                merkle_tree.tree[0][3].clone(),
-                merkle_tree.tree[1][0].clone()
-            ])
+            Vec::from([merkle_tree.tree[0][3], merkle_tree.tree[1][0]])
         );
         assert_eq!(
             merkle_tree.prove(3),
-            Vec::from([
-                merkle_tree.tree[0][2].clone(),
-                merkle_tree.tree[1][0].clone()
-            ])
+            Vec::from([merkle_tree.tree[0][2], merkle_tree.tree[1][0]])
         );
+    }
+
+    #[test]
+    fn test_prove_out_of_range() {
+        let hashes: Vec<Digest> = (0..4).map(|x| hash(x.to_string().as_ref())).collect();
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Sorted);
+        assert_eq!(
+            merkle_tree.try_prove(4),
+            Err(MerkleError::IndexOutOfRange {
+                index: 4,
+                leaf_count: 4
+            })
+        );
+        assert_eq!(merkle_tree.try_prove(3), Ok(merkle_tree.prove(3)));
+    }
+
+    #[test]
+    fn test_try_build_and_try_verify() {
+        assert_eq!(
+            MerkleTree::<Sha256>::try_build_with_scheme(&[], Scheme::Sorted).unwrap_err(),
+            MerkleError::NoLeaves
+        );
+        let hashes: Vec<Digest> = (0..5).map(|x| hash(x.to_string().as_ref())).collect();
+        let merkle_tree =
+            MerkleTree::<Sha256>::try_build_with_scheme(&hashes, Scheme::Sorted).unwrap();
+        let root = merkle_tree.get_root();
+        let proof = merkle_tree.try_prove(1).unwrap();
+        assert_eq!(try_verify::<Sha256>(&root, &hashes[1], &proof), Ok(()));
+        assert_eq!(
+            try_verify::<Sha256>(&root, &hashes[2], &proof),
+            Err(MerkleError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn test_build_unpaired_leaf_is_promoted() {
+        let hashes: Vec<Digest> = (0..3).map(|x| hash(x.to_string().as_ref())).collect();
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Sorted);
+        assert_eq!(merkle_tree.tree.len(), 3);
+        assert_eq!(merkle_tree.tree[1][1], hashes[2]);
         assert_eq!(
-            merkle_tree.prove(4),
-            Vec::from([
-                merkle_tree.tree[0][2].clone(),
-                merkle_tree.tree[1][0].clone()
-            ])
+            merkle_tree.get_root(),
+            Scheme::Sorted.hash_nodes::<Sha256>(
+                &Scheme::Sorted.hash_nodes::<Sha256>(&hashes[0], &hashes[1]),
+                &hashes[2]
+            )
         );
+        // The promoted leaf has no sibling on row 0
+        assert_eq!(merkle_tree.prove(2), Vec::from([merkle_tree.tree[1][0]]));
+    }
+
+    #[test]
+    fn test_prove_and_verify_any_leaf_count() {
+        for num_leaves in 1..=33usize {
+            let hashes: Vec<Digest> = (0..num_leaves)
+                .map(|x| hash(x.to_string().as_ref()))
+                .collect();
+            let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Sorted);
+            assert_eq!(merkle_tree.num_leaves, num_leaves);
+            assert_eq!(merkle_tree.tree.len(), find_depth(num_leaves));
+            let root_hash = merkle_tree.get_root();
+            for (index, leaf) in hashes.iter().enumerate() {
+                let proof = merkle_tree.prove(index);
+                assert!(proof.len() < merkle_tree.tree.len());
+                assert!(verify::<Sha256>(&root_hash, leaf, &proof));
+                assert!(!verify::<Sha256>(&root_hash, &hash(b"not a leaf"), &proof));
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_leaf() {
+        let hashes = vec![hash(b"0")];
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Sorted);
+        assert_eq!(merkle_tree.get_root(), hashes[0]);
+        assert!(merkle_tree.prove(0).is_empty());
+        assert!(verify::<Sha256>(
+            &merkle_tree.get_root(),
+            &hashes[0],
+            &merkle_tree.prove(0)
+        ));
     }
 
     #[test]
     fn test_find_path_leaf_to_root() {
-        let merkle_tree = MerkleTree {
+        let merkle_tree = MerkleTree::<Sha256> {
             tree: vec![Vec::new(); 4],
             num_leaves: 4,
+            scheme: Scheme::Sorted,
+            hasher: PhantomData,
         };
         // test data generated manually
         assert_eq!(merkle_tree.find_path_leaf_to_root(0), Vec::from([0, 0, 0]));
@@ -260,24 +738,252 @@ mod tests {
 
     #[test]
     fn test_depth() {
-        assert_eq!(MerkleTree::find_depth(1usize), 1usize); // 2**0
-        assert_eq!(MerkleTree::find_depth(2usize), 2usize); // 2**1
-        assert_eq!(MerkleTree::find_depth(3usize), 3usize);
-        assert_eq!(MerkleTree::find_depth(4usize), 3usize); // 2**2
-        assert_eq!(MerkleTree::find_depth(5usize), 4usize);
-        assert_eq!(MerkleTree::find_depth(8usize), 4usize); // 2**3
-        assert_eq!(MerkleTree::find_depth(9usize), 5usize);
-        assert_eq!(MerkleTree::find_depth(16usize), 5usize); // 2**4
-        assert_eq!(MerkleTree::find_depth(17usize), 6usize);
-        assert_eq!(MerkleTree::find_depth(32usize), 6usize); // 2**5
-        assert_eq!(MerkleTree::find_depth(33usize), 7usize);
+        assert_eq!(find_depth(1usize), 1usize); // 2**0
+        assert_eq!(find_depth(2usize), 2usize); // 2**1
+        assert_eq!(find_depth(3usize), 3usize);
+        assert_eq!(find_depth(4usize), 3usize); // 2**2
+        assert_eq!(find_depth(5usize), 4usize);
+        assert_eq!(find_depth(8usize), 4usize); // 2**3
+        assert_eq!(find_depth(9usize), 5usize);
+        assert_eq!(find_depth(16usize), 5usize); // 2**4
+        assert_eq!(find_depth(17usize), 6usize);
+        assert_eq!(find_depth(32usize), 6usize); // 2**5
+        assert_eq!(find_depth(33usize), 7usize);
     }
 
     #[test]
     fn test_find_node_sibling() {
-        assert_eq!(MerkleTree::find_node_sibling(0usize), 1usize);
-        assert_eq!(MerkleTree::find_node_sibling(1usize), 0usize);
-        assert_eq!(MerkleTree::find_node_sibling(5usize), 4usize);
-        assert_eq!(MerkleTree::find_node_sibling(6usize), 7usize);
+        assert_eq!(find_node_sibling(0usize), 1usize);
+        assert_eq!(find_node_sibling(1usize), 0usize);
+        assert_eq!(find_node_sibling(5usize), 4usize);
+        assert_eq!(find_node_sibling(6usize), 7usize);
+    }
+
+    #[test]
+    fn test_ordered_build() {
+        let hashes: Vec<Digest> = (0..4).map(|x| hash(x.to_string().as_ref())).collect();
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Ordered);
+        // Nodes are the hash of the hex text of the left child followed by the right child
+        let node = |left: &Digest, right: &Digest| hash((left.to_hex() + &right.to_hex()).as_ref());
+        let left = node(&hashes[0], &hashes[1]);
+        let right = node(&hashes[2], &hashes[3]);
+        assert_eq!(merkle_tree.tree[1], Vec::from([left, right]));
+        assert_eq!(merkle_tree.get_root(), node(&left, &right));
+        assert_ne!(
+            merkle_tree.get_root(),
+            MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Sorted).get_root()
+        );
+    }
+
+    #[test]
+    fn test_verify_at_index() {
+        for num_leaves in 1..=33usize {
+            let hashes: Vec<Digest> = (0..num_leaves)
+                .map(|x| hash(x.to_string().as_ref()))
+                .collect();
+            let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Ordered);
+            let root_hash = merkle_tree.get_root();
+            for (index, leaf) in hashes.iter().enumerate() {
+                let proof = merkle_tree.prove(index);
+                assert!(verify_at_index::<Sha256>(
+                    &root_hash, leaf, index, num_leaves, &proof
+                ));
+                // Out of range or another index
+                assert!(!verify_at_index::<Sha256>(
+                    &root_hash, leaf, num_leaves, num_leaves, &proof
+                ));
+                if num_leaves > 1 {
+                    let other_index = (index + 1) % num_leaves;
+                    assert!(!verify_at_index::<Sha256>(
+                        &root_hash,
+                        leaf,
+                        other_index,
+                        num_leaves,
+                        &proof
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_at_index_rejects_leaf_from_wrong_position() {
+        let hashes: Vec<Digest> = (0..4).map(|x| hash(x.to_string().as_ref())).collect();
+        let ordered_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Ordered);
+        // File 2 served with its own proof in place of file 3
+        let proof = ordered_tree.prove(2);
+        assert!(!verify_at_index::<Sha256>(
+            &ordered_tree.get_root(),
+            &hashes[2],
+            3,
+            4,
+            &proof
+        ));
+        assert!(!verify_at_index::<Sha256>(
+            &ordered_tree.get_root(),
+            &hashes[3],
+            3,
+            4,
+            &proof
+        ));
+
+        // The same substitution goes unnoticed with sorted hashing
+        let sorted_tree = MerkleTree::<Sha256>::build_with_scheme(&hashes, Scheme::Sorted);
+        let proof = sorted_tree.prove(2);
+        assert!(verify::<Sha256>(
+            &sorted_tree.get_root(),
+            &hashes[2],
+            &proof
+        ));
+        assert!(Scheme::Sorted.verify_at_index::<Sha256>(
+            &sorted_tree.get_root(),
+            &hashes[2],
+            3,
+            4,
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_scheme_versions() {
+        for scheme in [Scheme::Sorted, Scheme::Ordered, Scheme::DomainSeparated] {
+            assert_eq!(Scheme::from_version(scheme.version()), Some(scheme));
+        }
+        assert_eq!(Scheme::from_version(Scheme::LATEST.version() + 1), None);
+    }
+
+    #[test]
+    fn test_domain_separated_build_and_verify() {
+        let leaves: Vec<Digest> = (0..4)
+            .map(|x| Scheme::DomainSeparated.hash_leaf::<Sha256>(x.to_string().as_ref()))
+            .collect();
+        // Leaves are prefixed with 0x00
+        assert_eq!(leaves[0], hash(&[0x00, b'0']));
+        assert_ne!(leaves[0], hash(b"0"));
+
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::DomainSeparated);
+        // Nodes are the hash of 0x01 followed by the raw bytes of both children
+        let node = |left: &Digest, right: &Digest| {
+            let mut data = vec![0x01];
+            data.extend(left.as_bytes());
+            data.extend(right.as_bytes());
+            hash(&data)
+        };
+        let root = node(&node(&leaves[0], &leaves[1]), &node(&leaves[2], &leaves[3]));
+        assert_eq!(merkle_tree.get_root(), root);
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = merkle_tree.prove(index);
+            assert!(
+                Scheme::DomainSeparated.verify_at_index::<Sha256>(&root, leaf, index, 4, &proof)
+            );
+            assert!(!Scheme::Ordered.verify_at_index::<Sha256>(&root, leaf, index, 4, &proof));
+        }
+    }
+
+    #[test]
+    fn test_interior_node_is_not_a_leaf() {
+        let leaves: Vec<Digest> = (0..4)
+            .map(|x| Scheme::DomainSeparated.hash_leaf::<Sha256>(x.to_string().as_ref()))
+            .collect();
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::DomainSeparated);
+        // Present the node above leaves 0 and 1 as though its children were file contents
+        let mut forged_file = leaves[0].as_bytes().to_vec();
+        forged_file.extend(leaves[1].as_bytes());
+        let forged_leaf = Scheme::DomainSeparated.hash_leaf::<Sha256>(&forged_file);
+        assert_ne!(forged_leaf, merkle_tree.tree[1][0]);
+        assert!(!Scheme::DomainSeparated.verify_at_index::<Sha256>(
+            &merkle_tree.get_root(),
+            &forged_leaf,
+            0,
+            2,
+            &[merkle_tree.tree[1][1]]
+        ));
+    }
+
+    /// Build trees of every shape with the hasher and check each proof verifies
+    fn check_hasher_build_and_verify<H: MerkleHasher>() {
+        for num_leaves in 1..=9usize {
+            let leaves: Vec<Digest> = (0..num_leaves)
+                .map(|x| Scheme::LATEST.hash_leaf::<H>(x.to_string().as_ref()))
+                .collect();
+            let merkle_tree = MerkleTree::<H>::build_with_scheme(&leaves, Scheme::LATEST);
+            let root_hash = merkle_tree.get_root();
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_tree.prove(index);
+                assert!(Scheme::LATEST
+                    .verify_at_index::<H>(&root_hash, leaf, index, num_leaves, &proof));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sha256_hasher() {
+        check_hasher_build_and_verify::<Sha256>();
+    }
+
+    #[cfg(feature = "sha512_256")]
+    #[test]
+    fn test_sha512_256_hasher() {
+        check_hasher_build_and_verify::<hasher::Sha512_256>();
+    }
+
+    #[cfg(feature = "sha3")]
+    #[test]
+    fn test_sha3_256_hasher() {
+        check_hasher_build_and_verify::<hasher::Sha3_256>();
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_blake3_hasher() {
+        check_hasher_build_and_verify::<hasher::Blake3>();
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_roots_differ_between_hashers() {
+        let leaves: Vec<Digest> = (0..4).map(|x| hash(x.to_string().as_ref())).collect();
+        let sha256_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        // BLAKE3 digests from elsewhere can be used as leaves directly
+        let blake3_tree = MerkleTree::<hasher::Blake3>::build_with_scheme(&leaves, Scheme::LATEST);
+        assert_ne!(sha256_tree.get_root(), blake3_tree.get_root());
+    }
+
+    #[test]
+    fn test_push_matches_build() {
+        let leaves = test_util::leaves(33);
+        for scheme in [Scheme::Sorted, Scheme::Ordered, Scheme::DomainSeparated] {
+            let mut merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves[..1], scheme);
+            for num_leaves in 2..=leaves.len() {
+                let root = merkle_tree.push(leaves[num_leaves - 1]);
+                let rebuilt =
+                    MerkleTree::<Sha256>::build_with_scheme(&leaves[..num_leaves], scheme);
+                assert_eq!(root, rebuilt.get_root());
+                assert_eq!(merkle_tree.tree, rebuilt.tree);
+                assert_eq!(merkle_tree.num_leaves, num_leaves);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extend_matches_build() {
+        let leaves: Vec<Digest> = (0..20).map(|x| hash(x.to_string().as_ref())).collect();
+        let mut merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves[..5], Scheme::LATEST);
+        let root = merkle_tree.extend(&leaves[5..]);
+        let rebuilt = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        assert_eq!(root, rebuilt.get_root());
+        assert_eq!(merkle_tree.tree, rebuilt.tree);
+        // Extending by nothing leaves the root alone
+        assert_eq!(merkle_tree.extend(&[]), root);
+        for (index, leaf) in leaves.iter().enumerate() {
+            assert!(Scheme::LATEST.verify_at_index::<Sha256>(
+                &root,
+                leaf,
+                index,
+                leaves.len(),
+                &merkle_tree.prove(index)
+            ));
+        }
     }
 }
@@ -0,0 +1,344 @@
+use crate::{Digest, MerkleError, MerkleHasher, Scheme};
+use std::marker::PhantomData;
+
+/// Scheme used to hash nodes of a mountain range
+const MMR_SCHEME: Scheme = Scheme::DomainSeparated;
+
+/// Merkle Mountain Range: an append-only list of perfect binary trees, the mountains, one for each
+/// bit set in the number of leaves
+///
+/// Appending a leaf merges the mountains of equal height it completes and never changes an existing
+/// node, so the nodes of every earlier size are a prefix of the current nodes and proofs can be
+/// made against any of those sizes. The root bags the peaks from right to left, which gives the
+/// same root as a `MerkleTree` built from the same leaves with `Scheme::DomainSeparated`.
+///
+/// The server does not keep one, as its files can be replaced in place and a mountain range could
+/// only follow that by being rebuilt, which invalidates proofs against earlier sizes. It suits
+/// collections which are only ever appended to, and serialises so that it can be stored between
+/// appends.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MerkleMountainRange<H: MerkleHasher> {
+    /// Every node in the order it was added: each leaf followed by the parents it completes
+    ///
+    /// For example, with 4 leaves:
+    ///
+    /// position:  0  1  2  3  4  5  6
+    /// height:    0  0  1  0  0  1  2
+    pub nodes: Vec<Digest>,
+    pub num_leaves: usize,
+    #[serde(skip)]
+    pub hasher: PhantomData<H>,
+}
+
+/// Proof that a leaf is in a mountain range of a given size
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MmrProof {
+    pub index: usize,
+    pub leaf_count: usize,
+    /// Siblings on the path from the leaf to the peak of its mountain
+    pub siblings: Vec<Digest>,
+    /// Peaks of every other mountain, from left to right
+    pub peaks: Vec<Digest>,
+}
+
+impl<H: MerkleHasher> MerkleMountainRange<H> {
+    pub fn new() -> Self {
+        MerkleMountainRange {
+            nodes: Vec::new(),
+            num_leaves: 0,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Take a list of leaf hashes and append each of them in order
+    pub fn build(leaves: &[Digest]) -> Self {
+        let mut mmr = MerkleMountainRange::new();
+        for leaf in leaves {
+            mmr.append(*leaf);
+        }
+        mmr
+    }
+
+    /// Append a leaf and return its index
+    pub fn append(&mut self, leaf: Digest) -> usize {
+        let mut current = leaf;
+        self.nodes.push(current);
+        // Each trailing 1 bit of the old number of leaves is a mountain of that height which the
+        // new leaf completes a pair with
+        for height in 0..self.num_leaves.trailing_ones() {
+            let left_sibling = self.nodes[self.nodes.len() - (2 << height)];
+            current = MMR_SCHEME.hash_nodes::<H>(&left_sibling, &current);
+            self.nodes.push(current);
+        }
+        self.num_leaves += 1;
+        self.num_leaves - 1
+    }
+
+    /// Return the root of the mountain range as it is now, or None if it has no leaves
+    pub fn get_root(&self) -> Option<Digest> {
+        self.root_at(self.num_leaves)
+    }
+
+    /// Return the root of the mountain range when it had the given number of leaves, or None if
+    /// that is zero or more than it has now
+    pub fn root_at(&self, leaf_count: usize) -> Option<Digest> {
+        if leaf_count > self.num_leaves {
+            return None;
+        }
+        let peaks: Vec<Digest> = mountains(leaf_count)
+            .map(|mountain| self.nodes[mountain.peak_position()])
+            .collect();
+        bag_peaks::<H>(&peaks)
+    }
+
+    /// Create a proof for the leaf at an index against the root when there were `leaf_count` leaves
+    ///
+    /// Panics if the index is not that of a leaf at that size. See `try_prove`.
+    pub fn prove(&self, index: usize, leaf_count: usize) -> MmrProof {
+        self.try_prove(index, leaf_count)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `prove`, but returns an error rather than panicking if the index is out of range
+    pub fn try_prove(&self, index: usize, leaf_count: usize) -> Result<MmrProof, MerkleError> {
+        if index >= leaf_count || leaf_count > self.num_leaves {
+            return Err(MerkleError::IndexOutOfRange {
+                index,
+                leaf_count: leaf_count.min(self.num_leaves),
+            });
+        }
+        let mut siblings = Vec::new();
+        let mut peaks = Vec::new();
+        for mountain in mountains(leaf_count) {
+            if !mountain.contains(index) {
+                peaks.push(self.nodes[mountain.peak_position()]);
+                continue;
+            }
+            // Walk down from the peak, taking the sibling of the subtree holding the leaf
+            let local_index = index - mountain.first_leaf;
+            let mut start = mountain.first_position;
+            for height in (1..=mountain.height).rev() {
+                // Each child of a subtree of this height has 2^height - 1 nodes
+                let child_len = (1 << height) - 1;
+                if local_index & (1 << (height - 1)) == 0 {
+                    siblings.push(self.nodes[start + 2 * child_len - 1]);
+                } else {
+                    siblings.push(self.nodes[start + child_len - 1]);
+                    start += child_len;
+                }
+            }
+        }
+        siblings.reverse();
+        Ok(MmrProof {
+            index,
+            leaf_count,
+            siblings,
+            peaks,
+        })
+    }
+}
+
+impl<H: MerkleHasher> Default for MerkleMountainRange<H> {
+    fn default() -> Self {
+        MerkleMountainRange::new()
+    }
+}
+
+/// Take a root hash, a leaf hash and a proof and return true if the proof validates the leaf at the
+/// proof's index in a mountain range of the proof's size
+pub fn verify_mmr<H: MerkleHasher>(
+    root_hash: &Digest,
+    leaf_hash: &Digest,
+    proof: &MmrProof,
+) -> bool {
+    if proof.index >= proof.leaf_count {
+        return false;
+    }
+    let num_mountains = proof.leaf_count.count_ones() as usize;
+    if proof.peaks.len() + 1 != num_mountains {
+        return false;
+    }
+    let (position, mountain) = match mountains(proof.leaf_count)
+        .enumerate()
+        .find(|(_, mountain)| mountain.contains(proof.index))
+    {
+        Some(found) => found,
+        None => return false,
+    };
+    if proof.siblings.len() != mountain.height as usize {
+        return false;
+    }
+
+    let local_index = proof.index - mountain.first_leaf;
+    let mut peak = *leaf_hash;
+    for (height, sibling) in proof.siblings.iter().enumerate() {
+        peak = if local_index & (1 << height) == 0 {
+            MMR_SCHEME.hash_nodes::<H>(&peak, sibling)
+        } else {
+            MMR_SCHEME.hash_nodes::<H>(sibling, &peak)
+        };
+    }
+
+    let mut peaks = proof.peaks.clone();
+    peaks.insert(position, peak);
+    bag_peaks::<H>(&peaks) == Some(*root_hash)
+}
+
+/// Fold the peaks into one root from right to left
+fn bag_peaks<H: MerkleHasher>(peaks: &[Digest]) -> Option<Digest> {
+    let mut peaks = peaks.iter().rev();
+    let mut root = *peaks.next()?;
+    for peak in peaks {
+        root = MMR_SCHEME.hash_nodes::<H>(peak, &root);
+    }
+    Some(root)
+}
+
+/// A perfect binary tree within a mountain range
+#[derive(Debug, Clone, Copy)]
+struct Mountain {
+    height: u32,
+    /// Index of the leftmost leaf
+    first_leaf: usize,
+    /// Position of the first node
+    first_position: usize,
+}
+
+impl Mountain {
+    fn contains(&self, index: usize) -> bool {
+        index >= self.first_leaf && index - self.first_leaf < 1 << self.height
+    }
+
+    /// Position of the peak, the last node of the mountain to be added
+    fn peak_position(&self) -> usize {
+        self.first_position + (2 << self.height) - 2
+    }
+}
+
+/// The mountains of a range with the given number of leaves, from left to right
+fn mountains(leaf_count: usize) -> impl Iterator<Item = Mountain> {
+    let mut first_leaf = 0;
+    let mut first_position = 0;
+    (0..usize::BITS)
+        .rev()
+        .filter(move |height| leaf_count & (1 << height) != 0)
+        .map(move |height| {
+            let mountain = Mountain {
+                height,
+                first_leaf,
+                first_position,
+            };
+            first_leaf += 1 << height;
+            first_position += (2 << height) - 1;
+            mountain
+        })
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::test_util::leaves;
+    use crate::{MerkleTree, Sha256};
+
+    #[test]
+    fn test_append_layout() {
+        let leaves = leaves(4);
+        let mmr = MerkleMountainRange::<Sha256>::build(&leaves);
+        assert_eq!(mmr.nodes.len(), 7);
+        assert_eq!(mmr.nodes[3], leaves[2]);
+        let left = MMR_SCHEME.hash_nodes::<Sha256>(&leaves[0], &leaves[1]);
+        let right = MMR_SCHEME.hash_nodes::<Sha256>(&leaves[2], &leaves[3]);
+        assert_eq!(mmr.nodes[2], left);
+        assert_eq!(mmr.nodes[5], right);
+        assert_eq!(mmr.nodes[6], MMR_SCHEME.hash_nodes::<Sha256>(&left, &right));
+        assert_eq!(MerkleMountainRange::<Sha256>::new().get_root(), None);
+    }
+
+    #[test]
+    fn test_root_matches_merkle_tree() {
+        let leaves = leaves(40);
+        let mut mmr = MerkleMountainRange::<Sha256>::new();
+        for (index, leaf) in leaves.iter().enumerate() {
+            assert_eq!(mmr.append(*leaf), index);
+            let merkle_tree =
+                MerkleTree::<Sha256>::build_with_scheme(&leaves[..=index], MMR_SCHEME);
+            assert_eq!(mmr.get_root(), Some(merkle_tree.get_root()));
+            // Two nodes for each leaf, less one for each mountain
+            assert_eq!(
+                mmr.nodes.len(),
+                2 * mmr.num_leaves - mmr.num_leaves.count_ones() as usize
+            );
+        }
+    }
+
+    #[test]
+    fn test_proofs_against_historical_sizes() {
+        let leaves = leaves(23);
+        let mmr = MerkleMountainRange::<Sha256>::build(&leaves);
+        for leaf_count in 1..=leaves.len() {
+            let root = mmr.root_at(leaf_count).unwrap();
+            for index in 0..leaf_count {
+                let proof = mmr.prove(index, leaf_count);
+                assert!(verify_mmr::<Sha256>(&root, &leaves[index], &proof));
+                assert!(!verify_mmr::<Sha256>(
+                    &root,
+                    &leaves[(index + 1) % leaves.len()],
+                    &proof
+                ));
+            }
+        }
+        // A proof made at an earlier size stays valid against that size's root
+        let old_mmr = MerkleMountainRange::<Sha256>::build(&leaves[..10]);
+        let old_proof = old_mmr.prove(7, 10);
+        assert_eq!(mmr.prove(7, 10), old_proof);
+        assert!(verify_mmr::<Sha256>(
+            &old_mmr.get_root().unwrap(),
+            &leaves[7],
+            &old_proof
+        ));
+    }
+
+    #[test]
+    fn test_prove_out_of_range() {
+        let mmr = MerkleMountainRange::<Sha256>::build(&leaves(5));
+        assert_eq!(
+            mmr.try_prove(5, 5),
+            Err(MerkleError::IndexOutOfRange {
+                index: 5,
+                leaf_count: 5
+            })
+        );
+        assert!(mmr.try_prove(0, 6).is_err());
+        assert_eq!(mmr.root_at(6), None);
+    }
+
+    #[test]
+    fn test_verify_rejects_bad_proofs() {
+        let leaves = leaves(11);
+        let mmr = MerkleMountainRange::<Sha256>::build(&leaves);
+        let root = mmr.get_root().unwrap();
+        let proof = mmr.prove(9, 11);
+        assert!(verify_mmr::<Sha256>(&root, &leaves[9], &proof));
+
+        let mut other_index = proof.clone();
+        other_index.index = 8;
+        assert!(!verify_mmr::<Sha256>(&root, &leaves[9], &other_index));
+        let mut other_size = proof.clone();
+        other_size.leaf_count = 12;
+        assert!(!verify_mmr::<Sha256>(&root, &leaves[9], &other_size));
+        let mut missing_peak = proof.clone();
+        missing_peak.peaks.pop();
+        assert!(!verify_mmr::<Sha256>(&root, &leaves[9], &missing_peak));
+        let mut extra_sibling = proof;
+        extra_sibling.siblings.push(leaves[0]);
+        assert!(!verify_mmr::<Sha256>(&root, &leaves[9], &extra_sibling));
+    }
+
+    #[test]
+    fn test_serialised_form() {
+        let mmr = MerkleMountainRange::<Sha256>::build(&leaves(6));
+        let json = serde_json::to_string(&mmr).unwrap();
+        let parsed: MerkleMountainRange<Sha256> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, mmr);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{Digest, MerkleError, MerkleHasher, MerkleTree, Proof, Scheme, Sha256};
+
+/// Two Merkle trees over the same items, one built with SHA-256 and one with the hash function `H`
+///
+/// A proof holds one path for each tree and only verifies if both do, so forging one needs a second
+/// pre-image under both hash functions rather than one. `H` should be a hash function other than
+/// SHA-256, or the second tree adds nothing.
+#[derive(Debug)]
+pub struct MultiHashTree<H: MerkleHasher> {
+    pub sha256: MerkleTree<Sha256>,
+    pub other: MerkleTree<H>,
+}
+
+/// Prefix of the bytes hashed into `MultiHashRoot::commitment`, so that the commitment can never be
+/// taken for a leaf or node of either tree
+const COMMITMENT_CONTEXT: &[u8] = b"merkle_tree multi-hash root v1";
+
+/// Combined root commitment of a `MultiHashTree`: the root of each tree, and the hash function the
+/// second was built with
+///
+/// The two roots are independent, so a verifier must hold and check both. Checking only one gives no
+/// more protection than a single tree with that hash function. `MultiHashProof::verify` always checks
+/// both, and `commitment` binds both into one digest for publishing or signing.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultiHashRoot {
+    pub sha256: Digest,
+    /// `MerkleHasher::ID` of the hash function the second tree was built with
+    pub other_hash_id: u8,
+    pub other: Digest,
+}
+
+impl MultiHashRoot {
+    /// SHA-256 digest over the context prefix, the SHA-256 root, the second hash function id and the
+    /// second root
+    ///
+    /// Anyone holding the commitment can check that a `MultiHashRoot` they are sent is the one it was
+    /// made from, then verify proofs against both of its roots. The commitment itself only rests on
+    /// SHA-256, so if SHA-256 is broken the roots must be kept rather than the commitment alone.
+    pub fn commitment(&self) -> Digest {
+        Sha256::hash_parts(&[
+            COMMITMENT_CONTEXT,
+            self.sha256.as_bytes(),
+            &[self.other_hash_id],
+            self.other.as_bytes(),
+        ])
+    }
+
+    /// Return true if the roots are the ones a commitment was made from
+    pub fn matches_commitment(&self, commitment: &Digest) -> bool {
+        self.commitment() == *commitment
+    }
+}
+
+/// Proof that an item is in a `MultiHashTree` at an index, with one path for each tree
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MultiHashProof {
+    pub sha256: Proof,
+    pub other: Proof,
+}
+
+impl<H: MerkleHasher> MultiHashTree<H> {
+    /// Hash each item as a leaf with both hash functions and build both trees with a scheme
+    ///
+    /// Panics if there are no items. See `try_build`.
+    pub fn build<T: AsRef<[u8]> + Sync>(items: &[T], scheme: Scheme) -> MultiHashTree<H> {
+        Self::try_build(items, scheme).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `build`, but returns an error rather than panicking if there are no items
+    pub fn try_build<T: AsRef<[u8]> + Sync>(
+        items: &[T],
+        scheme: Scheme,
+    ) -> Result<MultiHashTree<H>, MerkleError> {
+        Ok(MultiHashTree {
+            sha256: MerkleTree::try_build_with_scheme(
+                &scheme.hash_leaves::<Sha256, T>(items),
+                scheme,
+            )?,
+            other: MerkleTree::try_build_with_scheme(&scheme.hash_leaves::<H, T>(items), scheme)?,
+        })
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.sha256.num_leaves
+    }
+
+    pub fn get_root(&self) -> MultiHashRoot {
+        MultiHashRoot {
+            sha256: self.sha256.get_root(),
+            other_hash_id: H::ID,
+            other: self.other.get_root(),
+        }
+    }
+
+    /// Create the proof for the item at an index
+    ///
+    /// Panics if the index is out of range. See `try_prove`.
+    pub fn prove(&self, index: usize) -> MultiHashProof {
+        self.try_prove(index)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `prove`, but returns an error rather than panicking if the index is out of range
+    pub fn try_prove(&self, index: usize) -> Result<MultiHashProof, MerkleError> {
+        Ok(MultiHashProof {
+            sha256: self.sha256.try_prove_leaf(index)?,
+            other: self.other.try_prove_leaf(index)?,
+        })
+    }
+}
+
+impl MultiHashProof {
+    /// Take a combined root, the scheme both trees were built with and an item and return true if
+    /// both paths validate the item at the proof's index, with the second tree built with the hash
+    /// function `H`
+    ///
+    /// The scheme must be the one the verifier recorded, not one taken from the proof.
+    pub fn verify<H: MerkleHasher>(
+        &self,
+        root: &MultiHashRoot,
+        scheme: Scheme,
+        item: &[u8],
+    ) -> bool {
+        self.try_verify::<H>(root, scheme, item).is_ok()
+    }
+
+    /// As `verify`, but returns an error saying why the proof failed rather than false
+    pub fn try_verify<H: MerkleHasher>(
+        &self,
+        root: &MultiHashRoot,
+        scheme: Scheme,
+        item: &[u8],
+    ) -> Result<(), MerkleError> {
+        if root.other_hash_id != H::ID {
+            return Err(MerkleError::UnexpectedHasher {
+                expected: H::ID,
+                found: root.other_hash_id,
+            });
+        }
+        // Both paths must be for the same item in trees of the same shape
+        if self.sha256.index != self.other.index || self.sha256.leaf_count != self.other.leaf_count
+        {
+            return Err(MerkleError::InvalidProof);
+        }
+        self.sha256.try_verify::<Sha256>(
+            &root.sha256,
+            scheme,
+            &scheme.hash_leaf::<Sha256>(item),
+        )?;
+        self.other
+            .try_verify::<H>(&root.other, scheme, &scheme.hash_leaf::<H>(item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in for a second hash function, so the tests need no feature beyond the default
+    struct TaggedSha256;
+
+    impl MerkleHasher for TaggedSha256 {
+        const ID: u8 = 200;
+        const NAME: &'static str = "Tagged SHA-256";
+
+        fn hash_parts(parts: &[&[u8]]) -> Digest {
+            let mut tagged = Vec::from([b"tagged".as_slice()]);
+            tagged.extend_from_slice(parts);
+            Sha256::hash_parts(&tagged)
+        }
+    }
+
+    fn items(num_items: usize) -> Vec<String> {
+        (0..num_items).map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        for num_items in 1..=9 {
+            let items = items(num_items);
+            let multi_hash_tree = MultiHashTree::<TaggedSha256>::build(&items, Scheme::LATEST);
+            let root = multi_hash_tree.get_root();
+            assert_eq!(multi_hash_tree.num_leaves(), num_items);
+            assert_ne!(root.sha256, root.other);
+            for (index, item) in items.iter().enumerate() {
+                let proof = multi_hash_tree.prove(index);
+                assert!(proof.verify::<TaggedSha256>(&root, Scheme::LATEST, item.as_ref()));
+                assert!(!proof.verify::<TaggedSha256>(&root, Scheme::LATEST, b"not an item"));
+            }
+            assert_eq!(
+                multi_hash_tree.try_prove(num_items),
+                Err(MerkleError::IndexOutOfRange {
+                    index: num_items,
+                    leaf_count: num_items
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_commitment_binds_both_roots() {
+        let root = MultiHashTree::<TaggedSha256>::build(&items(5), Scheme::LATEST).get_root();
+        let commitment = root.commitment();
+        assert!(root.matches_commitment(&commitment));
+        assert_ne!(commitment, root.sha256);
+        assert_ne!(commitment, root.other);
+
+        // Changing either root or the hash function changes the commitment
+        let mut changed = root;
+        changed.sha256 = Digest([0; 32]);
+        assert!(!changed.matches_commitment(&commitment));
+        let mut changed = root;
+        changed.other = Digest([0; 32]);
+        assert!(!changed.matches_commitment(&commitment));
+        let mut changed = root;
+        changed.other_hash_id = Sha256::ID;
+        assert!(!changed.matches_commitment(&commitment));
+    }
+
+    #[test]
+    fn test_every_path_must_verify() {
+        let items = items(6);
+        let multi_hash_tree = MultiHashTree::<TaggedSha256>::build(&items, Scheme::LATEST);
+        let root = multi_hash_tree.get_root();
+        let proof = multi_hash_tree.prove(2);
+
+        // A broken path in either tree fails the whole proof
+        let mut bad_sha256 = proof.clone();
+        bad_sha256.sha256.siblings[0] = Digest([0; 32]);
+        assert_eq!(
+            bad_sha256.try_verify::<TaggedSha256>(&root, Scheme::LATEST, items[2].as_ref()),
+            Err(MerkleError::InvalidProof)
+        );
+        let mut bad_other = proof.clone();
+        bad_other.other.siblings[0] = Digest([0; 32]);
+        assert!(!bad_other.verify::<TaggedSha256>(&root, Scheme::LATEST, items[2].as_ref()));
+
+        // Paths for different items
+        let mut mixed = proof.clone();
+        mixed.other = multi_hash_tree.other.prove_leaf(3);
+        assert!(!mixed.verify::<TaggedSha256>(&root, Scheme::LATEST, items[2].as_ref()));
+
+        // The second root claims a different hash function
+        assert_eq!(
+            proof.try_verify::<Sha256>(&root, Scheme::LATEST, items[2].as_ref()),
+            Err(MerkleError::UnexpectedHasher {
+                expected: Sha256::ID,
+                found: TaggedSha256::ID
+            })
+        );
+    }
+}
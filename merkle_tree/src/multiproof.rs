@@ -0,0 +1,268 @@
+use crate::{
+    find_node_sibling, find_parent_of_node, Digest, MerkleError, MerkleHasher, MerkleTree, Scheme,
+};
+
+/// Proof that several leaves are in a tree at given indices
+///
+/// Siblings shared between the paths of the proven leaves are included only once, and nodes which
+/// the verifier can compute from the leaves themselves are left out.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MultiProof {
+    pub leaf_count: usize,
+    pub scheme: Scheme,
+    /// Sibling hashes in the order they are consumed: row by row from the leaves up, and from left
+    /// to right within a row
+    pub nodes: Vec<Digest>,
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Create a single proof for all of the given leaf indices. An index given more than once is
+    /// proven once
+    ///
+    /// Panics if any index is out of range. See `try_prove_many`.
+    pub fn prove_many(&self, indices: &[usize]) -> MultiProof {
+        self.try_prove_many(indices)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `prove_many`, but returns an error rather than panicking if any index is out of range
+    pub fn try_prove_many(&self, indices: &[usize]) -> Result<MultiProof, MerkleError> {
+        for index in indices {
+            self.check_index(*index)?;
+        }
+        let mut known: Vec<usize> = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+
+        let mut nodes = Vec::new();
+        for row in self.tree.iter().take(self.tree.len() - 1) {
+            let mut parents = Vec::with_capacity(known.len());
+            let mut i = 0;
+            while i < known.len() {
+                let node = known[i];
+                let sibling = find_node_sibling(node);
+                if known.get(i + 1) == Some(&sibling) {
+                    // Both children are known so the verifier can compute the parent
+                    i += 1;
+                } else if sibling < row.len() {
+                    nodes.push(row[sibling]);
+                }
+                parents.push(find_parent_of_node(node));
+                i += 1;
+            }
+            known = parents;
+        }
+
+        Ok(MultiProof {
+            leaf_count: self.num_leaves,
+            scheme: self.scheme,
+            nodes,
+        })
+    }
+}
+
+/// Take a root hash, the scheme the tree was built with, a list of leaf indices along with their
+/// leaf hashes and a multi-proof and return true if the proof validates every leaf at its index
+///
+/// The scheme must be the one the verifier recorded, not one taken from the proof. Otherwise a
+/// server could prove the leaves under a weaker scheme, such as `Scheme::Sorted`, which does not
+/// bind leaves to their indices.
+pub fn verify_many<H: MerkleHasher>(
+    root_hash: &Digest,
+    scheme: Scheme,
+    leaves: &[(usize, Digest)],
+    proof: &MultiProof,
+) -> bool {
+    if proof.scheme != scheme || leaves.is_empty() || proof.leaf_count == 0 {
+        return false;
+    }
+    let mut known: Vec<(usize, Digest)> = leaves.to_vec();
+    known.sort_unstable_by_key(|(index, _)| *index);
+    // The same index may be given more than once but only with the same leaf
+    for pair in known.windows(2) {
+        if pair[0].0 == pair[1].0 && pair[0].1 != pair[1].1 {
+            return false;
+        }
+    }
+    known.dedup();
+    if known[known.len() - 1].0 >= proof.leaf_count {
+        return false;
+    }
+
+    let mut nodes = proof.nodes.iter();
+    let mut row_len = proof.leaf_count;
+    while row_len > 1 {
+        let mut parents = Vec::with_capacity(known.len());
+        let mut i = 0;
+        while i < known.len() {
+            let (node, hash) = known[i];
+            let sibling = find_node_sibling(node);
+            let parent_hash = if known.get(i + 1).map(|(index, _)| *index) == Some(sibling) {
+                i += 1;
+                scheme.hash_nodes::<H>(&hash, &known[i].1)
+            } else if sibling < row_len {
+                let sibling_hash = match nodes.next() {
+                    Some(sibling_hash) => sibling_hash,
+                    None => return false,
+                };
+                if node < sibling {
+                    scheme.hash_nodes::<H>(&hash, sibling_hash)
+                } else {
+                    scheme.hash_nodes::<H>(sibling_hash, &hash)
+                }
+            } else {
+                // A node without a sibling is promoted to the next row unchanged
+                hash
+            };
+            parents.push((find_parent_of_node(node), parent_hash));
+            i += 1;
+        }
+        known = parents;
+        row_len = row_len.div_ceil(2);
+    }
+    nodes.next().is_none() && known[0].1 == *root_hash
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::test_util::{build_tree, build_tree_with_scheme};
+    use crate::Sha256;
+
+    #[test]
+    fn test_prove_many_omits_computable_nodes() {
+        let (leaves, merkle_tree) = build_tree(8);
+        // Leaves 0 and 1 are siblings, their parent's sibling is the only node needed from row 1
+        let proof = merkle_tree.prove_many(&[0, 1]);
+        assert_eq!(
+            proof.nodes,
+            Vec::from([merkle_tree.tree[1][1], merkle_tree.tree[2][1]])
+        );
+        // Leaves 0 and 3 share the sibling at row 2
+        let proof = merkle_tree.prove_many(&[3, 0]);
+        assert_eq!(
+            proof.nodes,
+            Vec::from([
+                merkle_tree.tree[0][1],
+                merkle_tree.tree[0][2],
+                merkle_tree.tree[2][1]
+            ])
+        );
+        assert!(verify_many::<Sha256>(
+            &merkle_tree.get_root(),
+            Scheme::LATEST,
+            &[(0, leaves[0]), (3, leaves[3])],
+            &proof
+        ));
+        // Proving every leaf needs no nodes at all
+        let all: Vec<usize> = (0..8).collect();
+        assert!(merkle_tree.prove_many(&all).nodes.is_empty());
+    }
+
+    #[test]
+    fn test_try_prove_many() {
+        let (_, merkle_tree) = build_tree(5);
+        assert_eq!(
+            merkle_tree.try_prove_many(&[1, 5]),
+            Err(MerkleError::IndexOutOfRange {
+                index: 5,
+                leaf_count: 5
+            })
+        );
+        // Duplicates are proven once
+        assert_eq!(
+            merkle_tree.try_prove_many(&[4, 1, 4]),
+            Ok(merkle_tree.prove_many(&[1, 4]))
+        );
+    }
+
+    #[test]
+    fn test_verify_many_any_subset() {
+        for num_leaves in 1..=11usize {
+            let (leaves, merkle_tree) = build_tree(num_leaves);
+            let root_hash = merkle_tree.get_root();
+            // Every subset of leaves, as a bit mask
+            for mask in 1..(1u32 << num_leaves) {
+                let indices: Vec<usize> =
+                    (0..num_leaves).filter(|i| mask & (1 << i) != 0).collect();
+                let proof = merkle_tree.prove_many(&indices);
+                let claimed: Vec<(usize, Digest)> =
+                    indices.iter().map(|i| (*i, leaves[*i])).collect();
+                assert!(verify_many::<Sha256>(
+                    &root_hash,
+                    Scheme::LATEST,
+                    &claimed,
+                    &proof
+                ));
+                // Never larger than the single proofs together
+                let single_proofs: usize =
+                    indices.iter().map(|i| merkle_tree.prove(*i).len()).sum();
+                assert!(proof.nodes.len() <= single_proofs);
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_many_rejects_wrong_leaves() {
+        let (leaves, merkle_tree) = build_tree(7);
+        let root_hash = merkle_tree.get_root();
+        let proof = merkle_tree.prove_many(&[2, 5]);
+        // Swapped positions
+        assert!(!verify_many::<Sha256>(
+            &root_hash,
+            Scheme::LATEST,
+            &[(2, leaves[5]), (5, leaves[2])],
+            &proof
+        ));
+        // Unproven index
+        assert!(!verify_many::<Sha256>(
+            &root_hash,
+            Scheme::LATEST,
+            &[(2, leaves[2]), (6, leaves[6])],
+            &proof
+        ));
+        // Conflicting duplicate
+        assert!(!verify_many::<Sha256>(
+            &root_hash,
+            Scheme::LATEST,
+            &[(2, leaves[2]), (2, leaves[3]), (5, leaves[5])],
+            &proof
+        ));
+        // Nothing to prove
+        assert!(!verify_many::<Sha256>(
+            &root_hash,
+            Scheme::LATEST,
+            &[],
+            &proof
+        ));
+        // Duplicates of the same leaf are accepted
+        assert!(verify_many::<Sha256>(
+            &root_hash,
+            Scheme::LATEST,
+            &[(5, leaves[5]), (2, leaves[2]), (5, leaves[5])],
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_verify_many_rejects_other_scheme() {
+        // A valid proof from a tree built with the legacy sorted scheme, which does not bind leaves
+        // to their indices, is not accepted for a root recorded as domain separated
+        let (leaves, sorted_tree) = build_tree_with_scheme(6, Scheme::Sorted);
+        let root_hash = sorted_tree.get_root();
+        let proof = sorted_tree.prove_many(&[1, 4]);
+        let claimed = [(1, leaves[1]), (4, leaves[4])];
+        assert!(verify_many::<Sha256>(
+            &root_hash,
+            Scheme::Sorted,
+            &claimed,
+            &proof
+        ));
+        assert!(!verify_many::<Sha256>(
+            &root_hash,
+            Scheme::DomainSeparated,
+            &claimed,
+            &proof
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{Digest, MerkleHasher, Scheme};
+use rayon::prelude::*;
+
+/// Number of items below which hashing stays on the calling thread, where splitting the work
+/// would cost more than it saves
+pub(crate) const MIN_PARALLEL_LEN: usize = 1024;
+
+/// Hash the contents of each file into a leaf across threads
+pub(crate) fn hash_leaves<H: MerkleHasher, T: AsRef<[u8]> + Sync>(
+    scheme: &Scheme,
+    data: &[T],
+) -> Vec<Digest> {
+    data.par_iter()
+        .with_min_len(MIN_PARALLEL_LEN / 2)
+        .map(|item| scheme.hash_leaf::<H>(item.as_ref()))
+        .collect()
+}
+
+/// Hash pairs of nodes on a row across threads to build the row above it
+pub(crate) fn hash_row<H: MerkleHasher>(scheme: &Scheme, row: &[Digest]) -> Vec<Digest> {
+    row.par_chunks(2)
+        .with_min_len(MIN_PARALLEL_LEN / 2)
+        .map(|pair| scheme.hash_pair::<H>(pair))
+        .collect()
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::{MerkleTree, Sha256};
+
+    fn data(num_items: usize) -> Vec<String> {
+        (0..num_items).map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parallel_leaves_match_serial() {
+        for num_items in [1, MIN_PARALLEL_LEN - 1, MIN_PARALLEL_LEN, 5000] {
+            let data = data(num_items);
+            for scheme in [Scheme::Sorted, Scheme::Ordered, Scheme::DomainSeparated] {
+                let serial: Vec<Digest> = data
+                    .iter()
+                    .map(|item| scheme.hash_leaf::<Sha256>(item.as_ref()))
+                    .collect();
+                assert_eq!(hash_leaves::<Sha256, String>(&scheme, &data), serial);
+                assert_eq!(scheme.hash_leaves::<Sha256, String>(&data), serial);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_build_matches_serial() {
+        for num_leaves in [
+            MIN_PARALLEL_LEN,
+            MIN_PARALLEL_LEN + 1,
+            3 * MIN_PARALLEL_LEN + 7,
+        ] {
+            for scheme in [Scheme::Sorted, Scheme::Ordered, Scheme::DomainSeparated] {
+                let leaves = scheme.hash_leaves::<Sha256, String>(&data(num_leaves));
+                let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, scheme);
+
+                // Build each row again on this thread only
+                let mut row = leaves;
+                for parallel_row in merkle_tree.tree.iter().skip(1) {
+                    row = row
+                        .chunks(2)
+                        .map(|pair| scheme.hash_pair::<Sha256>(pair))
+                        .collect();
+                    assert_eq!(&row, parallel_row);
+                }
+                assert_eq!(row.len(), 1);
+                assert_eq!(
+                    hash_row::<Sha256>(&scheme, &merkle_tree.tree[0]),
+                    merkle_tree.tree[1]
+                );
+            }
+        }
+    }
+}
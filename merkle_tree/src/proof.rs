@@ -0,0 +1,339 @@
+#[cfg(feature = "std")]
+use crate::MerkleTree;
+use crate::{Digest, MerkleError, MerkleHasher, Scheme, DIGEST_LEN};
+use alloc::vec::Vec;
+
+/// Version of the binary encoding written by `Proof::to_bytes`
+pub const PROOF_FORMAT_VERSION: u8 = 1;
+
+/// Proof that a leaf is in a tree at an index, along with everything needed to verify it
+///
+/// The binary encoding is, in order: the format version, the scheme version and the hash function
+/// id as one byte each, the index and the number of leaves as LEB128 varints, then the raw bytes of
+/// each sibling.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    pub format_version: u8,
+    pub scheme: Scheme,
+    /// `MerkleHasher::ID` of the hash function the tree was built with
+    pub hash_id: u8,
+    pub index: usize,
+    pub leaf_count: usize,
+    pub siblings: Vec<Digest>,
+}
+
+#[cfg(feature = "std")]
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Create a self-describing proof for the leaf at an index
+    ///
+    /// Panics if the index is out of range. See `try_prove_leaf`.
+    pub fn prove_leaf(&self, index: usize) -> Proof {
+        self.try_prove_leaf(index)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `prove_leaf`, but returns an error rather than panicking if the index is out of range
+    pub fn try_prove_leaf(&self, index: usize) -> Result<Proof, MerkleError> {
+        Ok(Proof {
+            format_version: PROOF_FORMAT_VERSION,
+            scheme: self.scheme,
+            hash_id: H::ID,
+            index,
+            leaf_count: self.num_leaves,
+            siblings: self.try_prove(index)?,
+        })
+    }
+}
+
+impl Proof {
+    /// Take a root hash, the scheme the tree was built with and a leaf hash and return true if the
+    /// proof validates the leaf at the proof's index in a tree built with the hash function `H`
+    ///
+    /// The scheme must be the one the verifier recorded, not one taken from the proof. Otherwise a
+    /// server could prove the leaf under a weaker scheme, such as `Scheme::Sorted`, which does not
+    /// bind leaves to their indices.
+    pub fn verify<H: MerkleHasher>(
+        &self,
+        root_hash: &Digest,
+        scheme: Scheme,
+        leaf_hash: &Digest,
+    ) -> bool {
+        self.try_verify::<H>(root_hash, scheme, leaf_hash).is_ok()
+    }
+
+    /// As `verify`, but returns an error saying why the proof failed rather than false
+    pub fn try_verify<H: MerkleHasher>(
+        &self,
+        root_hash: &Digest,
+        scheme: Scheme,
+        leaf_hash: &Digest,
+    ) -> Result<(), MerkleError> {
+        if self.hash_id != H::ID {
+            return Err(MerkleError::UnexpectedHasher {
+                expected: H::ID,
+                found: self.hash_id,
+            });
+        }
+        if self.scheme != scheme {
+            return Err(MerkleError::UnexpectedScheme {
+                expected: scheme,
+                found: self.scheme,
+            });
+        }
+        if self.index >= self.leaf_count {
+            return Err(MerkleError::IndexOutOfRange {
+                index: self.index,
+                leaf_count: self.leaf_count,
+            });
+        }
+        if !scheme.verify_at_index::<H>(
+            root_hash,
+            leaf_hash,
+            self.index,
+            self.leaf_count,
+            &self.siblings,
+        ) {
+            return Err(MerkleError::InvalidProof);
+        }
+        Ok(())
+    }
+
+    /// Encode the proof in the compact binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(3 + 2 * 10 + self.siblings.len() * DIGEST_LEN);
+        bytes.push(self.format_version);
+        bytes.push(self.scheme.version());
+        bytes.push(self.hash_id);
+        write_varint(&mut bytes, self.index as u64);
+        write_varint(&mut bytes, self.leaf_count as u64);
+        for sibling in &self.siblings {
+            bytes.extend_from_slice(sibling.as_bytes());
+        }
+        bytes
+    }
+
+    /// Decode a proof from the compact binary format. Returns None if the format or scheme version
+    /// is unknown or the bytes are not a whole proof
+    pub fn from_bytes(bytes: &[u8]) -> Option<Proof> {
+        let (header, mut rest) = bytes.split_first_chunk::<3>()?;
+        let [format_version, scheme_version, hash_id] = *header;
+        if format_version != PROOF_FORMAT_VERSION {
+            return None;
+        }
+        let scheme = Scheme::from_version(scheme_version)?;
+        let index = read_varint(&mut rest)?.try_into().ok()?;
+        let leaf_count = read_varint(&mut rest)?.try_into().ok()?;
+
+        let chunks = rest.chunks_exact(DIGEST_LEN);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+        let siblings = chunks
+            .map(|chunk| Digest(chunk.try_into().unwrap()))
+            .collect();
+
+        Some(Proof {
+            format_version,
+            scheme,
+            hash_id,
+            index,
+            leaf_count,
+            siblings,
+        })
+    }
+}
+
+/// Append an unsigned LEB128 varint: 7 bits at a time, least significant first, with the high bit
+/// set on every byte but the last
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Read an unsigned LEB128 varint from the front of the input, advancing past it. Returns None if
+/// the input ends first, the value does not fit in 64 bits or the encoding is not the shortest one,
+/// so that each proof has exactly one encoding
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        let bits = u64::from(byte & 0x7f);
+        if bits << shift >> shift != bits {
+            return None;
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            // A last byte of 0 after the first only pads the encoding with zero bits
+            if *byte == 0 && shift > 0 {
+                return None;
+            }
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::test_util::{build_tree, build_tree_with_scheme};
+    use crate::Sha256;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_proof_round_trip() {
+        let (leaves, merkle_tree) = build_tree(300);
+        for index in [0, 1, 127, 128, 299] {
+            let proof = merkle_tree.prove_leaf(index);
+            assert_eq!(proof.siblings, merkle_tree.prove(index));
+            assert!(proof.verify::<Sha256>(
+                &merkle_tree.get_root(),
+                Scheme::LATEST,
+                &leaves[index]
+            ));
+
+            let bytes = proof.to_bytes();
+            // The index takes one varint byte below 128 and the 300 leaves take two
+            let index_len = if index < 128 { 1 } else { 2 };
+            assert_eq!(
+                bytes.len(),
+                3 + index_len + 2 + proof.siblings.len() * DIGEST_LEN
+            );
+            assert_eq!(Proof::from_bytes(&bytes), Some(proof.clone()));
+
+            let json = serde_json::to_string(&proof).unwrap();
+            assert_eq!(serde_json::from_str::<Proof>(&json).unwrap(), proof);
+        }
+    }
+
+    #[test]
+    fn test_binary_layout() {
+        // Proof for the second of two leaves, which builds without std
+        let leaves = [b"0", b"1"].map(|item| Scheme::LATEST.hash_leaf::<Sha256>(item));
+        let root = Scheme::LATEST.hash_nodes::<Sha256>(&leaves[0], &leaves[1]);
+        let mut bytes = Vec::from([PROOF_FORMAT_VERSION, 2, Sha256::ID, 1, 2]);
+        bytes.extend_from_slice(leaves[0].as_bytes());
+
+        let proof = Proof::from_bytes(&bytes).unwrap();
+        assert_eq!(proof.scheme, Scheme::LATEST);
+        assert_eq!((proof.index, proof.leaf_count), (1, 2));
+        assert!(proof.verify::<Sha256>(&root, Scheme::LATEST, &leaves[1]));
+        assert!(!proof.verify::<Sha256>(&root, Scheme::LATEST, &leaves[0]));
+        assert_eq!(proof.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_varint() {
+        for value in [0, 1, 0x7f, 0x80, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+            let mut input = bytes.as_slice();
+            assert_eq!(read_varint(&mut input), Some(value));
+            assert!(input.is_empty());
+        }
+        assert_eq!(read_varint(&mut [0xac, 0x02].as_slice()), Some(300));
+        // Padded with zero bits, which would give another encoding of the same value
+        assert_eq!(read_varint(&mut [0x80, 0x00].as_slice()), None);
+        assert_eq!(read_varint(&mut [0xac, 0x82, 0x00].as_slice()), None);
+        assert_eq!(read_varint(&mut [0x00].as_slice()), Some(0));
+        // Truncated, and too large for 64 bits
+        assert_eq!(read_varint(&mut [0x80].as_slice()), None);
+        assert_eq!(read_varint(&mut [0xff; 10].as_slice()), None);
+        assert_eq!(
+            read_varint(
+                &mut [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02].as_slice()
+            ),
+            None
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_bytes_rejects_bad_input() {
+        let (_, merkle_tree) = build_tree(5);
+        let bytes = merkle_tree.prove_leaf(2).to_bytes();
+        assert_eq!(Proof::from_bytes(&[]), None);
+        assert_eq!(Proof::from_bytes(&bytes[..bytes.len() - 1]), None);
+        let mut unknown_version = bytes.clone();
+        unknown_version[0] = PROOF_FORMAT_VERSION + 1;
+        assert_eq!(Proof::from_bytes(&unknown_version), None);
+        let mut unknown_scheme = bytes.clone();
+        unknown_scheme[1] = 0xff;
+        assert_eq!(Proof::from_bytes(&unknown_scheme), None);
+        // The index of 2 padded to two bytes
+        let mut padded = bytes[..3].to_vec();
+        padded.extend_from_slice(&[0x82, 0x00]);
+        padded.extend_from_slice(&bytes[4..]);
+        assert_eq!(Proof::from_bytes(&padded), None);
+        // A number of leaves which overflows 64 bits
+        let mut overflowing = bytes[..4].to_vec();
+        overflowing
+            .extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        overflowing.extend_from_slice(&bytes[5..]);
+        assert_eq!(Proof::from_bytes(&overflowing), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_verify_checks_metadata() {
+        let (leaves, merkle_tree) = build_tree(6);
+        let root = merkle_tree.get_root();
+        let proof = merkle_tree.prove_leaf(4);
+        let mut other_hasher = proof.clone();
+        other_hasher.hash_id = Sha256::ID + 1;
+        assert_eq!(
+            other_hasher.try_verify::<Sha256>(&root, Scheme::LATEST, &leaves[4]),
+            Err(MerkleError::UnexpectedHasher {
+                expected: Sha256::ID,
+                found: Sha256::ID + 1
+            })
+        );
+        let mut other_index = proof.clone();
+        other_index.index = 5;
+        assert_eq!(
+            other_index.try_verify::<Sha256>(&root, Scheme::LATEST, &leaves[4]),
+            Err(MerkleError::InvalidProof)
+        );
+        other_index.index = 6;
+        assert_eq!(
+            other_index.try_verify::<Sha256>(&root, Scheme::LATEST, &leaves[4]),
+            Err(MerkleError::IndexOutOfRange {
+                index: 6,
+                leaf_count: 6
+            })
+        );
+        let mut other_scheme = proof;
+        other_scheme.scheme = Scheme::Ordered;
+        assert_eq!(
+            other_scheme.try_verify::<Sha256>(&root, Scheme::LATEST, &leaves[4]),
+            Err(MerkleError::UnexpectedScheme {
+                expected: Scheme::LATEST,
+                found: Scheme::Ordered
+            })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_verify_rejects_other_scheme() {
+        // A valid proof from a tree built with the legacy sorted scheme, which does not bind leaves
+        // to their indices, is not accepted for a root recorded as domain separated
+        let (leaves, sorted_tree) = build_tree_with_scheme(6, Scheme::Sorted);
+        let root = sorted_tree.get_root();
+        let proof = sorted_tree.prove_leaf(2);
+        assert!(proof.verify::<Sha256>(&root, Scheme::Sorted, &leaves[2]));
+        assert_eq!(
+            proof.try_verify::<Sha256>(&root, Scheme::DomainSeparated, &leaves[2]),
+            Err(MerkleError::UnexpectedScheme {
+                expected: Scheme::DomainSeparated,
+                found: Scheme::Sorted
+            })
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{Digest, MerkleHasher, Scheme};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+/// Computes the root of a tree from its leaves one at a time, without building the tree
+///
+/// Only the roots of the complete subtrees to the left of the next leaf are kept, at most one for
+/// each height, so memory grows with the log of the number of leaves. The root is the same as that
+/// of `MerkleTree::build_with_scheme` over the same leaves.
+#[derive(Debug, Clone)]
+pub struct MerkleRootBuilder<H: MerkleHasher> {
+    scheme: Scheme,
+    /// Roots of complete subtrees along with their heights, tallest first
+    stack: Vec<(u32, Digest)>,
+    num_leaves: usize,
+    hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> MerkleRootBuilder<H> {
+    pub fn new(scheme: Scheme) -> Self {
+        MerkleRootBuilder {
+            scheme,
+            stack: Vec::new(),
+            num_leaves: 0,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Number of leaves pushed so far
+    pub fn num_leaves(&self) -> usize {
+        self.num_leaves
+    }
+
+    /// Add the next leaf, merging each pair of complete subtrees of equal height it completes
+    pub fn push(&mut self, leaf: Digest) {
+        let mut height = 0;
+        let mut node = leaf;
+        while let Some((top_height, top)) = self.stack.last() {
+            if *top_height != height {
+                break;
+            }
+            node = self.scheme.hash_nodes::<H>(top, &node);
+            height += 1;
+            self.stack.pop();
+        }
+        self.stack.push((height, node));
+        self.num_leaves += 1;
+    }
+
+    /// Return the root of the tree over the leaves pushed so far, or None if there are none
+    ///
+    /// Subtrees left incomplete are joined from the right, as the last node of a row with an odd
+    /// number of nodes is promoted in `MerkleTree`.
+    pub fn finalize(&self) -> Option<Digest> {
+        let mut subtrees = self.stack.iter().rev();
+        let mut root = subtrees.next()?.1;
+        for (_, left) in subtrees {
+            root = self.scheme.hash_nodes::<H>(left, &root);
+        }
+        Some(root)
+    }
+}
+
+impl<H: MerkleHasher> Extend<Digest> for MerkleRootBuilder<H> {
+    fn extend<I: IntoIterator<Item = Digest>>(&mut self, leaves: I) {
+        for leaf in leaves {
+            self.push(leaf);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::{MerkleTree, Sha256};
+
+    #[test]
+    fn test_root_matches_build() {
+        for scheme in [Scheme::Sorted, Scheme::Ordered, Scheme::DomainSeparated] {
+            let mut builder = MerkleRootBuilder::<Sha256>::new(scheme);
+            assert_eq!(builder.finalize(), None);
+            let mut leaves = Vec::new();
+            for x in 0..70usize {
+                let leaf = scheme.hash_leaf::<Sha256>(x.to_string().as_ref());
+                leaves.push(leaf);
+                builder.push(leaf);
+
+                let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, scheme);
+                assert_eq!(builder.finalize(), Some(merkle_tree.get_root()));
+                assert_eq!(builder.num_leaves(), leaves.len());
+                // One subtree for each bit set in the number of leaves
+                assert_eq!(builder.stack.len(), leaves.len().count_ones() as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extend_from_iterator() {
+        let leaves =
+            (0..1000usize).map(|x| Scheme::LATEST.hash_leaf::<Sha256>(x.to_string().as_ref()));
+        let mut builder = MerkleRootBuilder::<Sha256>::new(Scheme::LATEST);
+        builder.extend(leaves.clone());
+
+        let merkle_tree =
+            MerkleTree::<Sha256>::build_with_scheme(&leaves.collect::<Vec<_>>(), Scheme::LATEST);
+        assert_eq!(builder.finalize(), Some(merkle_tree.get_root()));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{Digest, MerkleHasher};
+use alloc::string::String;
+use core::fmt;
+
+/// Number of bytes in a salt
+pub const SALT_LEN: usize = 32;
+
+/// Prefix for a leaf hashed with its salt, distinct from the prefixes of `Scheme::DomainSeparated`
+/// leaves and nodes and of chunked file leaves
+const SALTED_LEAF_PREFIX: u8 = 0x03;
+
+/// Random bytes hashed into the leaf of one file
+///
+/// A proof for one file reveals the leaves of others as siblings. Without a salt anyone holding a
+/// proof can confirm a guess of a sibling's contents by hashing it, which matters for short or
+/// predictable files. A salted leaf reveals nothing until the salt is disclosed along with its
+/// file. Each file needs its own salt from a secure random source.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Salt(pub [u8; SALT_LEN]);
+
+impl Salt {
+    pub fn as_bytes(&self) -> &[u8; SALT_LEN] {
+        &self.0
+    }
+
+    /// Lowercase hex encoding of the salt
+    pub fn to_hex(&self) -> String {
+        base16ct::lower::encode_string(&self.0)
+    }
+
+    /// Parse a salt from hex. Returns None unless the input is exactly `2 * SALT_LEN` hex characters
+    pub fn from_hex(hex: &str) -> Option<Salt> {
+        if hex.len() != 2 * SALT_LEN {
+            return None;
+        }
+        let mut bytes = [0u8; SALT_LEN];
+        base16ct::mixed::decode(hex, &mut bytes).ok()?;
+        Some(Salt(bytes))
+    }
+}
+
+impl fmt::Display for Salt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for Salt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Salt({})", self)
+    }
+}
+
+/// Hash a salt into the leaf of a file, whether the file was hashed whole with a scheme or as a
+/// `ChunkTree`. The salted leaf takes the place of the leaf in the collection tree
+pub fn salt_leaf<H: MerkleHasher>(salt: &Salt, leaf: &Digest) -> Digest {
+    H::hash_parts(&[&[SALTED_LEAF_PREFIX], salt.as_bytes(), leaf.as_bytes()])
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::{Scheme, Sha256};
+
+    #[test]
+    fn test_salted_leaf_depends_on_salt() {
+        let leaf = Scheme::LATEST.hash_leaf::<Sha256>(b"yes");
+        let salted = salt_leaf::<Sha256>(&Salt([1; SALT_LEN]), &leaf);
+        assert_ne!(salted, leaf);
+        assert_eq!(salted, salt_leaf::<Sha256>(&Salt([1; SALT_LEN]), &leaf));
+        assert_ne!(salted, salt_leaf::<Sha256>(&Salt([2; SALT_LEN]), &leaf));
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let salt = Salt([0xab; SALT_LEN]);
+        assert_eq!(salt.to_hex(), "ab".repeat(SALT_LEN));
+        assert_eq!(Salt::from_hex(&salt.to_hex()), Some(salt));
+        assert_eq!(Salt::from_hex("abab"), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_salted_leaves_prove_and_verify() {
+        use crate::MerkleTree;
+
+        let files = ["0", "1", "2", "3", "4"];
+        let salts: Vec<Salt> = (0..files.len() as u8)
+            .map(|x| Salt([x; SALT_LEN]))
+            .collect();
+        let leaves: Vec<Digest> = files
+            .iter()
+            .zip(&salts)
+            .map(|(file, salt)| {
+                salt_leaf::<Sha256>(salt, &Scheme::LATEST.hash_leaf::<Sha256>(file.as_ref()))
+            })
+            .collect();
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        let root = merkle_tree.get_root();
+        for (index, file) in files.iter().enumerate() {
+            let proof = merkle_tree.prove_leaf(index);
+            let leaf = Scheme::LATEST.hash_leaf::<Sha256>(file.as_ref());
+            assert!(proof.verify::<Sha256>(&root, Scheme::LATEST, &salt_leaf::<Sha256>(&salts[index], &leaf)));
+            assert!(!proof.verify::<Sha256>(&root, Scheme::LATEST, &leaf));
+            let other_salt = &salts[(index + 1) % files.len()];
+            assert!(!proof.verify::<Sha256>(&root, Scheme::LATEST, &salt_leaf::<Sha256>(other_salt, &leaf)));
+        }
+    }
+}
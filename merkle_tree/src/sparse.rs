@@ -0,0 +1,383 @@
+use crate::{Digest, MerkleHasher, DIGEST_LEN, LEAF_PREFIX, NODE_PREFIX};
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+
+/// Number of levels below the root of a sparse tree, one for each bit of a key
+pub const SPARSE_DEPTH: usize = 8 * DIGEST_LEN;
+
+/// Merkle tree with a leaf for every possible 256 bit key, almost all of which are empty
+///
+/// The bits of a key, most significant first, give the path from the root to its leaf: 0 for left
+/// and 1 for right. A key with a value has the leaf `hash(0x00 || key || value)` and every other
+/// leaf is all zeros. Parents are `hash(0x01 || left || right)`, so a subtree with no values has a
+/// default hash which depends only on its height. Only nodes which differ from their default are
+/// stored.
+#[derive(Debug, Clone)]
+pub struct SparseMerkleTree<H: MerkleHasher> {
+    values: BTreeMap<Digest, Digest>,
+    /// Nodes which are not the default for their height, by height above the leaves and the key
+    /// bits leading to them. Bits below the node are zero.
+    nodes: HashMap<(usize, Digest), Digest>,
+    /// Hash of an empty subtree at each height, from the empty leaf up to the empty root
+    defaults: Vec<Digest>,
+    hasher: PhantomData<H>,
+}
+
+/// Proof that a key has a given value in a sparse tree, or that it has none
+///
+/// Siblings which are the default for their height are left out and marked as such in the bitmap.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SparseProof {
+    /// Bit `h` is set when the sibling at height `h` above the leaves is included in `siblings`
+    pub bitmap: [u8; SPARSE_DEPTH / 8],
+    /// Included siblings from the leaf up to the root
+    pub siblings: Vec<Digest>,
+}
+
+impl<H: MerkleHasher> SparseMerkleTree<H> {
+    /// Create a tree in which every key is empty
+    pub fn new() -> Self {
+        let mut defaults = Vec::with_capacity(SPARSE_DEPTH + 1);
+        defaults.push(Digest::default());
+        for height in 0..SPARSE_DEPTH {
+            defaults.push(hash_sparse_nodes::<H>(&defaults[height], &defaults[height]));
+        }
+        SparseMerkleTree {
+            values: BTreeMap::new(),
+            nodes: HashMap::new(),
+            defaults,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Number of keys with a value
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, key: &Digest) -> Option<&Digest> {
+        self.values.get(key)
+    }
+
+    pub fn get_root(&self) -> Digest {
+        self.node(SPARSE_DEPTH, &Digest::default())
+    }
+
+    /// Set the value of a key, returning its previous value
+    pub fn insert(&mut self, key: Digest, value: Digest) -> Option<Digest> {
+        let old_value = self.values.insert(key, value);
+        self.rehash_path(&key, hash_sparse_leaf::<H>(&key, &value));
+        old_value
+    }
+
+    /// Delete the value of a key, returning it if there was one
+    pub fn remove(&mut self, key: &Digest) -> Option<Digest> {
+        let old_value = self.values.remove(key)?;
+        self.rehash_path(key, self.defaults[0]);
+        Some(old_value)
+    }
+
+    /// Create a proof for a key, which shows its value if it has one and that it is empty otherwise
+    pub fn prove(&self, key: &Digest) -> SparseProof {
+        let mut bitmap = [0u8; SPARSE_DEPTH / 8];
+        let mut siblings = Vec::new();
+        for height in 0..SPARSE_DEPTH {
+            let sibling = self.node(height, &flip_bit(&key_prefix(key, height), height));
+            if sibling != self.defaults[height] {
+                bitmap[height / 8] |= 1 << (height % 8);
+                siblings.push(sibling);
+            }
+        }
+        SparseProof { bitmap, siblings }
+    }
+
+    /// Node at a height above the leaves, given by the key bits leading to it
+    fn node(&self, height: usize, prefix: &Digest) -> Digest {
+        match self.nodes.get(&(height, *prefix)) {
+            Some(node) => *node,
+            None => self.defaults[height],
+        }
+    }
+
+    /// Set the leaf of a key and recompute every node on its path to the root
+    fn rehash_path(&mut self, key: &Digest, leaf: Digest) {
+        let mut current = leaf;
+        for height in 0..=SPARSE_DEPTH {
+            let prefix = key_prefix(key, height);
+            if current == self.defaults[height] {
+                self.nodes.remove(&(height, prefix));
+            } else {
+                self.nodes.insert((height, prefix), current);
+            }
+            if height == SPARSE_DEPTH {
+                break;
+            }
+            let sibling = self.node(height, &flip_bit(&prefix, height));
+            current = if bit_at_height(key, height) {
+                hash_sparse_nodes::<H>(&sibling, &current)
+            } else {
+                hash_sparse_nodes::<H>(&current, &sibling)
+            };
+        }
+    }
+}
+
+impl<H: MerkleHasher> Default for SparseMerkleTree<H> {
+    fn default() -> Self {
+        SparseMerkleTree::new()
+    }
+}
+
+impl SparseProof {
+    /// Compute the root reached from a leaf at the position of a key, or None if the proof does
+    /// not have exactly one sibling for each bit set in its bitmap
+    fn compute_root<H: MerkleHasher>(&self, key: &Digest, leaf: Digest) -> Option<Digest> {
+        let mut siblings = self.siblings.iter();
+        let mut current = leaf;
+        let mut default = Digest::default();
+        for height in 0..SPARSE_DEPTH {
+            let sibling = if self.bitmap[height / 8] & (1 << (height % 8)) != 0 {
+                *siblings.next()?
+            } else {
+                default
+            };
+            current = if bit_at_height(key, height) {
+                hash_sparse_nodes::<H>(&sibling, &current)
+            } else {
+                hash_sparse_nodes::<H>(&current, &sibling)
+            };
+            default = hash_sparse_nodes::<H>(&default, &default);
+        }
+        match siblings.next() {
+            Some(_) => None,
+            None => Some(current),
+        }
+    }
+}
+
+/// Take a sparse tree root, a key, a value and a proof and return true if the key has that value
+pub fn verify_membership<H: MerkleHasher>(
+    root_hash: &Digest,
+    key: &Digest,
+    value: &Digest,
+    proof: &SparseProof,
+) -> bool {
+    proof.compute_root::<H>(key, hash_sparse_leaf::<H>(key, value)) == Some(*root_hash)
+}
+
+/// Take a sparse tree root, a key and a proof and return true if the key has no value
+pub fn verify_non_membership<H: MerkleHasher>(
+    root_hash: &Digest,
+    key: &Digest,
+    proof: &SparseProof,
+) -> bool {
+    proof.compute_root::<H>(key, Digest::default()) == Some(*root_hash)
+}
+
+fn hash_sparse_leaf<H: MerkleHasher>(key: &Digest, value: &Digest) -> Digest {
+    H::hash_parts(&[&[LEAF_PREFIX], key.as_bytes(), value.as_bytes()])
+}
+
+fn hash_sparse_nodes<H: MerkleHasher>(left: &Digest, right: &Digest) -> Digest {
+    H::hash_parts(&[&[NODE_PREFIX], left.as_bytes(), right.as_bytes()])
+}
+
+/// Bit of a key which chooses between the children of its ancestor at `height + 1`. True for the
+/// right child
+fn bit_at_height(key: &Digest, height: usize) -> bool {
+    let bit = SPARSE_DEPTH - 1 - height;
+    key.0[bit / 8] & (0x80 >> (bit % 8)) != 0
+}
+
+/// Key bits leading to the ancestor of a key at a height, with the bits below it cleared
+fn key_prefix(key: &Digest, height: usize) -> Digest {
+    let mut prefix = *key;
+    for bit in SPARSE_DEPTH - height..SPARSE_DEPTH {
+        prefix.0[bit / 8] &= !(0x80 >> (bit % 8));
+    }
+    prefix
+}
+
+/// Prefix of the sibling of the node with the given prefix at a height
+fn flip_bit(prefix: &Digest, height: usize) -> Digest {
+    let bit = SPARSE_DEPTH - 1 - height;
+    let mut sibling = *prefix;
+    sibling.0[bit / 8] ^= 0x80 >> (bit % 8);
+    sibling
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::Sha256;
+
+    fn key(name: &str) -> Digest {
+        Sha256::hash(name.as_bytes())
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let tree = SparseMerkleTree::<Sha256>::new();
+        let mut default = Digest::default();
+        for _ in 0..SPARSE_DEPTH {
+            default = hash_sparse_nodes::<Sha256>(&default, &default);
+        }
+        assert_eq!(tree.get_root(), default);
+        let proof = tree.prove(&key("a"));
+        assert!(proof.siblings.is_empty());
+        assert!(verify_non_membership::<Sha256>(
+            &tree.get_root(),
+            &key("a"),
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_membership_and_non_membership() {
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        for x in 0..20 {
+            tree.insert(key(&x.to_string()), key(&format!("value {}", x)));
+        }
+        let root = tree.get_root();
+        for x in 0..20 {
+            let proof = tree.prove(&key(&x.to_string()));
+            let value = key(&format!("value {}", x));
+            assert!(verify_membership::<Sha256>(
+                &root,
+                &key(&x.to_string()),
+                &value,
+                &proof
+            ));
+            assert!(!verify_non_membership::<Sha256>(
+                &root,
+                &key(&x.to_string()),
+                &proof
+            ));
+            // Default siblings are left out, so only about log2(20) remain
+            assert!(proof.siblings.len() < 16);
+        }
+        for x in 20..40 {
+            let proof = tree.prove(&key(&x.to_string()));
+            assert!(verify_non_membership::<Sha256>(
+                &root,
+                &key(&x.to_string()),
+                &proof
+            ));
+            assert!(!verify_membership::<Sha256>(
+                &root,
+                &key(&x.to_string()),
+                &key("value 0"),
+                &proof
+            ));
+        }
+    }
+
+    #[test]
+    fn test_root_independent_of_history() {
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        let empty_root = tree.get_root();
+        for x in 0..10 {
+            tree.insert(key(&x.to_string()), key("value"));
+        }
+        let mut reversed = SparseMerkleTree::<Sha256>::new();
+        for x in (0..10).rev() {
+            reversed.insert(key(&x.to_string()), key("value"));
+        }
+        assert_eq!(tree.get_root(), reversed.get_root());
+
+        // Overwriting then restoring a value restores the root
+        let root = tree.get_root();
+        assert_eq!(tree.insert(key("3"), key("other")), Some(key("value")));
+        assert_ne!(tree.get_root(), root);
+        tree.insert(key("3"), key("value"));
+        assert_eq!(tree.get_root(), root);
+
+        // Removing every value leaves nothing stored
+        for x in 0..10 {
+            assert_eq!(tree.remove(&key(&x.to_string())), Some(key("value")));
+        }
+        assert_eq!(tree.remove(&key("0")), None);
+        assert!(tree.is_empty());
+        assert!(tree.nodes.is_empty());
+        assert_eq!(tree.get_root(), empty_root);
+    }
+
+    #[test]
+    fn test_verify_rejects_bad_proofs() {
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        for x in 0..8 {
+            tree.insert(key(&x.to_string()), key("value"));
+        }
+        let root = tree.get_root();
+        let proof = tree.prove(&key("1"));
+        // Wrong value or key
+        assert!(!verify_membership::<Sha256>(
+            &root,
+            &key("1"),
+            &key("other"),
+            &proof
+        ));
+        assert!(!verify_membership::<Sha256>(
+            &root,
+            &key("2"),
+            &key("value"),
+            &proof
+        ));
+        // Missing and extra siblings
+        let mut short = proof.clone();
+        short.siblings.pop();
+        assert!(!verify_membership::<Sha256>(
+            &root,
+            &key("1"),
+            &key("value"),
+            &short
+        ));
+        let mut long = proof.clone();
+        long.siblings.push(key("extra"));
+        assert!(!verify_membership::<Sha256>(
+            &root,
+            &key("1"),
+            &key("value"),
+            &long
+        ));
+        // Non-membership of a removed key against the old root
+        tree.remove(&key("1"));
+        let proof = tree.prove(&key("1"));
+        assert!(!verify_non_membership::<Sha256>(&root, &key("1"), &proof));
+        assert!(verify_non_membership::<Sha256>(
+            &tree.get_root(),
+            &key("1"),
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_adjacent_keys() {
+        // Keys differing only in their last bit are siblings at the leaves
+        let mut tree = SparseMerkleTree::<Sha256>::new();
+        let left = Digest([0u8; DIGEST_LEN]);
+        let mut right = left;
+        right.0[DIGEST_LEN - 1] = 1;
+        tree.insert(left, key("left"));
+        tree.insert(right, key("right"));
+        let proof = tree.prove(&left);
+        assert_eq!(proof.siblings.len(), 1);
+        assert_eq!(proof.bitmap[0], 1);
+        assert!(verify_membership::<Sha256>(
+            &tree.get_root(),
+            &left,
+            &key("left"),
+            &proof
+        ));
+        assert!(verify_membership::<Sha256>(
+            &tree.get_root(),
+            &right,
+            &key("right"),
+            &tree.prove(&right)
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::proof::PROOF_FORMAT_VERSION;
+use crate::{
+    find_node_sibling, find_parent_of_node, Digest, MerkleError, MerkleHasher, MerkleTree, Proof,
+    Scheme, DIGEST_LEN,
+};
+use std::marker::PhantomData;
+
+/// Number of bytes before the first node of a stored tree
+pub const STORED_HEADER_LEN: usize = 10;
+
+/// Description of a tree stored with `MerkleTree::to_bytes`, which is enough to locate any node
+///
+/// A stored tree is the scheme version and the hash function id as one byte each, the number of
+/// leaves as a little endian u64, then the raw bytes of every node row by row from the leaves up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoredTreeHeader {
+    pub scheme: Scheme,
+    pub hash_id: u8,
+    pub leaf_count: usize,
+}
+
+impl StoredTreeHeader {
+    /// Parse the header from the first `STORED_HEADER_LEN` bytes of a stored tree
+    pub fn from_bytes(bytes: &[u8]) -> Result<StoredTreeHeader, MerkleError> {
+        let header: &[u8; STORED_HEADER_LEN] = bytes
+            .get(..STORED_HEADER_LEN)
+            .and_then(|header| header.try_into().ok())
+            .ok_or(MerkleError::InvalidStoredTree)?;
+        let scheme = Scheme::from_version(header[0]).ok_or(MerkleError::InvalidStoredTree)?;
+        let leaf_count = u64::from_le_bytes(header[2..].try_into().unwrap())
+            .try_into()
+            .map_err(|_| MerkleError::InvalidStoredTree)?;
+        if leaf_count == 0 {
+            return Err(MerkleError::InvalidStoredTree);
+        }
+        Ok(StoredTreeHeader {
+            scheme,
+            hash_id: header[1],
+            leaf_count,
+        })
+    }
+
+    fn to_bytes(self) -> [u8; STORED_HEADER_LEN] {
+        let mut header = [0u8; STORED_HEADER_LEN];
+        header[0] = self.scheme.version();
+        header[1] = self.hash_id;
+        header[2..].copy_from_slice(&(self.leaf_count as u64).to_le_bytes());
+        header
+    }
+
+    /// Number of nodes in each row of the tree, from the leaves up
+    fn row_lens(&self) -> impl Iterator<Item = usize> {
+        let mut row_len = self.leaf_count;
+        let mut done = false;
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let this_row = row_len;
+            done = row_len == 1;
+            row_len = row_len.div_ceil(2);
+            Some(this_row)
+        })
+    }
+
+    /// Number of bytes in the whole stored tree
+    pub fn stored_len(&self) -> usize {
+        STORED_HEADER_LEN + self.row_lens().sum::<usize>() * DIGEST_LEN
+    }
+
+    /// Byte offset of a node in the stored tree
+    pub fn node_offset(&self, row: usize, node: usize) -> usize {
+        let nodes_before: usize = self.row_lens().take(row).sum();
+        STORED_HEADER_LEN + (nodes_before + node) * DIGEST_LEN
+    }
+
+    /// Byte offsets of the siblings making up the proof for the leaf at an index, in proof order
+    pub fn proof_offsets(&self, index: usize) -> Result<Vec<usize>, MerkleError> {
+        if index >= self.leaf_count {
+            return Err(MerkleError::IndexOutOfRange {
+                index,
+                leaf_count: self.leaf_count,
+            });
+        }
+        let mut offsets = Vec::new();
+        let mut row_start = STORED_HEADER_LEN;
+        let mut node = index;
+        for row_len in self.row_lens() {
+            let sibling = find_node_sibling(node);
+            // A node without a sibling is promoted and contributes nothing
+            if sibling < row_len {
+                offsets.push(row_start + sibling * DIGEST_LEN);
+            }
+            row_start += row_len * DIGEST_LEN;
+            node = find_parent_of_node(node);
+        }
+        Ok(offsets)
+    }
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Encode the whole tree for storage. See `StoredTreeHeader` for the layout
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = StoredTreeHeader {
+            scheme: self.scheme,
+            hash_id: H::ID,
+            leaf_count: self.num_leaves,
+        };
+        let mut bytes = Vec::with_capacity(header.stored_len());
+        bytes.extend_from_slice(&header.to_bytes());
+        for row in &self.tree {
+            for node in row {
+                bytes.extend_from_slice(node.as_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Load a tree stored with `to_bytes`, without hashing any of its nodes again
+    pub fn from_bytes(bytes: &[u8]) -> Result<MerkleTree<H>, MerkleError> {
+        let header = StoredTreeHeader::from_bytes(bytes)?;
+        if header.hash_id != H::ID || bytes.len() != header.stored_len() {
+            return Err(MerkleError::InvalidStoredTree);
+        }
+        let mut nodes = bytes[STORED_HEADER_LEN..]
+            .chunks_exact(DIGEST_LEN)
+            .map(|chunk| Digest(chunk.try_into().unwrap()));
+        let tree = header
+            .row_lens()
+            .map(|row_len| nodes.by_ref().take(row_len).collect())
+            .collect();
+        Ok(MerkleTree {
+            tree,
+            num_leaves: header.leaf_count,
+            scheme: header.scheme,
+            hasher: PhantomData,
+        })
+    }
+}
+
+/// Create the proof for the leaf at an index of a stored tree, reading only its header and the
+/// proof's siblings
+///
+/// `read` takes a byte offset and a length and returns those bytes of the stored tree, or None if
+/// there is no stored tree or it is too short.
+pub fn prove_from_stored<H: MerkleHasher>(
+    index: usize,
+    mut read: impl FnMut(usize, usize) -> Option<Vec<u8>>,
+) -> Result<Proof, MerkleError> {
+    let header_bytes = read(0, STORED_HEADER_LEN).ok_or(MerkleError::InvalidStoredTree)?;
+    let header = StoredTreeHeader::from_bytes(&header_bytes)?;
+    if header.hash_id != H::ID {
+        return Err(MerkleError::InvalidStoredTree);
+    }
+    let siblings = header
+        .proof_offsets(index)?
+        .into_iter()
+        .map(|offset| {
+            let bytes = read(offset, DIGEST_LEN).ok_or(MerkleError::InvalidStoredTree)?;
+            let bytes = bytes
+                .try_into()
+                .map_err(|_| MerkleError::InvalidStoredTree)?;
+            Ok(Digest(bytes))
+        })
+        .collect::<Result<Vec<Digest>, MerkleError>>()?;
+    Ok(Proof {
+        format_version: PROOF_FORMAT_VERSION,
+        scheme: header.scheme,
+        hash_id: header.hash_id,
+        index,
+        leaf_count: header.leaf_count,
+        siblings,
+    })
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::test_util::build_tree;
+    use crate::Sha256;
+
+    #[test]
+    fn test_stored_tree_round_trip() {
+        for num_leaves in 1..=20 {
+            let (_, merkle_tree) = build_tree(num_leaves);
+            let bytes = merkle_tree.to_bytes();
+            let loaded = MerkleTree::<Sha256>::from_bytes(&bytes).unwrap();
+            assert_eq!(loaded.tree, merkle_tree.tree);
+            assert_eq!(loaded.num_leaves, num_leaves);
+            assert_eq!(loaded.scheme, merkle_tree.scheme);
+
+            let header = StoredTreeHeader::from_bytes(&bytes).unwrap();
+            assert_eq!(header.stored_len(), bytes.len());
+            for (row, nodes) in merkle_tree.tree.iter().enumerate() {
+                for (node, digest) in nodes.iter().enumerate() {
+                    let offset = header.node_offset(row, node);
+                    assert_eq!(&bytes[offset..offset + DIGEST_LEN], digest.as_bytes());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_prove_from_stored_matches_prove() {
+        for num_leaves in 1..=20 {
+            let (_, merkle_tree) = build_tree(num_leaves);
+            let bytes = merkle_tree.to_bytes();
+            for index in 0..num_leaves {
+                let mut reads = 0;
+                let proof = prove_from_stored::<Sha256>(index, |offset, len| {
+                    reads += 1;
+                    bytes.get(offset..offset + len).map(|slice| slice.to_vec())
+                })
+                .unwrap();
+                assert_eq!(proof, merkle_tree.prove_leaf(index));
+                // The header and one read for each sibling
+                assert_eq!(reads, 1 + proof.siblings.len());
+            }
+            assert_eq!(
+                prove_from_stored::<Sha256>(num_leaves, |offset, len| {
+                    bytes.get(offset..offset + len).map(|slice| slice.to_vec())
+                }),
+                Err(MerkleError::IndexOutOfRange {
+                    index: num_leaves,
+                    leaf_count: num_leaves
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_stored_tree() {
+        let (_, merkle_tree) = build_tree(5);
+        let bytes = merkle_tree.to_bytes();
+        assert!(MerkleTree::<Sha256>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(MerkleTree::<Sha256>::from_bytes(&bytes[..4]).is_err());
+        let mut other_hasher = bytes.clone();
+        other_hasher[1] = Sha256::ID + 1;
+        assert_eq!(
+            MerkleTree::<Sha256>::from_bytes(&other_hasher).unwrap_err(),
+            MerkleError::InvalidStoredTree
+        );
+        assert_eq!(
+            prove_from_stored::<Sha256>(0, |_, _| None),
+            Err(MerkleError::InvalidStoredTree)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(feature = "ed25519")]
+use crate::MerkleError;
+use crate::{Digest, MerkleHasher, MerkleTree, Scheme};
+#[cfg(feature = "ed25519")]
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+/// Prefix of the signed bytes of a tree head, so that a signature over one can never be passed off
+/// as a signature over anything else the key signs
+const TREE_HEAD_CONTEXT: &[u8] = b"merkle_tree tree head v1";
+
+/// What a server commits to for the collection it stores: the root of the Merkle tree over it, the
+/// number of leaves and how the tree was built, and when
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeHead {
+    pub root: Digest,
+    pub leaf_count: usize,
+    pub scheme: Scheme,
+    /// `MerkleHasher::ID` of the hash function the tree was built with
+    pub hash_id: u8,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Tree head with an Ed25519 signature over `TreeHead::to_bytes`, which anyone holding the
+/// signer's public key can check
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignedTreeHead {
+    pub tree_head: TreeHead,
+    #[serde(with = "crate::interface::hex_bytes")]
+    pub signature: Vec<u8>,
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Tree head for the current root of the tree, made at a time in seconds since the Unix epoch
+    pub fn tree_head(&self, timestamp: u64) -> TreeHead {
+        TreeHead {
+            root: self.get_root(),
+            leaf_count: self.num_leaves,
+            scheme: self.scheme,
+            hash_id: H::ID,
+            timestamp,
+        }
+    }
+}
+
+impl TreeHead {
+    /// Bytes which are signed: the context prefix, scheme version, hash function id, number of
+    /// leaves and timestamp as little endian `u64`s, then the root
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            TREE_HEAD_CONTEXT,
+            &[self.scheme.version(), self.hash_id],
+            &(self.leaf_count as u64).to_le_bytes(),
+            &self.timestamp.to_le_bytes(),
+            self.root.as_bytes(),
+        ]
+        .concat()
+    }
+
+    /// Sign the tree head with the server's key
+    #[cfg(feature = "ed25519")]
+    pub fn sign(self, signing_key: &SigningKey) -> SignedTreeHead {
+        use ed25519_dalek::Signer;
+        SignedTreeHead {
+            signature: signing_key.sign(&self.to_bytes()).to_vec(),
+            tree_head: self,
+        }
+    }
+}
+
+#[cfg(feature = "ed25519")]
+impl SignedTreeHead {
+    /// Return true if the signature over the tree head was made with the secret key of a public key
+    pub fn verify(&self, public_key: &VerifyingKey) -> bool {
+        self.try_verify(public_key).is_ok()
+    }
+
+    /// As `verify`, but returns an error rather than false
+    pub fn try_verify(&self, public_key: &VerifyingKey) -> Result<(), MerkleError> {
+        let signature = ed25519_dalek::Signature::from_slice(&self.signature)
+            .map_err(|_| MerkleError::InvalidSignature)?;
+        public_key
+            .verify_strict(&self.tree_head.to_bytes(), &signature)
+            .map_err(|_| MerkleError::InvalidSignature)
+    }
+}
+
+#[cfg(all(test, feature = "ed25519", feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::test_util::build_tree;
+    use crate::Sha256;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let public_key = signing_key.verifying_key();
+        let (_, merkle_tree) = build_tree(5);
+        let signed_tree_head = merkle_tree.tree_head(1_700_000_000).sign(&signing_key);
+        assert_eq!(signed_tree_head.tree_head.root, merkle_tree.get_root());
+        assert_eq!(signed_tree_head.tree_head.leaf_count, 5);
+        assert_eq!(signed_tree_head.tree_head.hash_id, Sha256::ID);
+        assert!(signed_tree_head.verify(&public_key));
+
+        // Another key did not sign it
+        let other_key = SigningKey::from_bytes(&[8; 32]).verifying_key();
+        assert_eq!(
+            signed_tree_head.try_verify(&other_key),
+            Err(MerkleError::InvalidSignature)
+        );
+
+        // Changing any field of the head breaks the signature
+        let mut changed = signed_tree_head.clone();
+        changed.tree_head.leaf_count += 1;
+        assert!(!changed.verify(&public_key));
+        let mut changed = signed_tree_head.clone();
+        changed.tree_head.timestamp += 1;
+        assert!(!changed.verify(&public_key));
+        let mut changed = signed_tree_head.clone();
+        changed.tree_head.scheme = Scheme::Ordered;
+        assert!(!changed.verify(&public_key));
+
+        let mut truncated = signed_tree_head;
+        truncated.signature.pop();
+        assert!(!truncated.verify(&public_key));
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let (_, merkle_tree) = build_tree(5);
+        let signed_tree_head = merkle_tree
+            .tree_head(1_700_000_000)
+            .sign(&SigningKey::from_bytes(&[7; 32]));
+        let json = serde_json::to_string(&signed_tree_head).unwrap();
+        assert!(json.contains(&base16ct::lower::encode_string(&signed_tree_head.signature)));
+        let parsed: SignedTreeHead = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, signed_tree_head);
+    }
+}
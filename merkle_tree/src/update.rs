@@ -0,0 +1,182 @@
+use crate::{Digest, MerkleError, MerkleHasher, MerkleTree, Scheme};
+
+/// Record of a leaf replaced in place, with the proof that only that leaf changed
+///
+/// The siblings on the path from a leaf to the root are not changed by updating the leaf, so one
+/// proof ties the old leaf to the old root and the new leaf to the new root.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LeafUpdate {
+    pub index: usize,
+    pub leaf_count: usize,
+    pub scheme: Scheme,
+    pub old_leaf: Digest,
+    pub new_leaf: Digest,
+    pub old_root: Digest,
+    pub new_root: Digest,
+    pub proof: Vec<Digest>,
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Replace the leaf at an index, recomputing only the nodes on its path to the root
+    ///
+    /// Panics if the index is out of range. See `try_update`.
+    pub fn update(&mut self, index: usize, new_leaf: Digest) -> LeafUpdate {
+        self.try_update(index, new_leaf)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `update`, but returns an error rather than panicking if the index is out of range
+    pub fn try_update(
+        &mut self,
+        index: usize,
+        new_leaf: Digest,
+    ) -> Result<LeafUpdate, MerkleError> {
+        let proof = self.try_prove(index)?;
+        let old_leaf = self.tree[0][index];
+        let old_root = self.get_root();
+
+        self.tree[0][index] = new_leaf;
+        self.rehash_path(index);
+
+        Ok(LeafUpdate {
+            index,
+            leaf_count: self.num_leaves,
+            scheme: self.scheme,
+            old_leaf,
+            new_leaf,
+            old_root,
+            new_root: self.get_root(),
+            proof,
+        })
+    }
+}
+
+/// Take the root held before an update, the scheme that tree was built with, the leaf expected to
+/// have been written and the update and return true if the update replaced only the leaf at its
+/// index with that leaf
+///
+/// The scheme must be the one the verifier recorded, not one taken from the update. Otherwise a
+/// server could prove the update under a weaker scheme, such as `Scheme::Sorted`, which does not
+/// bind leaves to their indices.
+pub fn verify_update<H: MerkleHasher>(
+    old_root: &Digest,
+    scheme: Scheme,
+    new_leaf: &Digest,
+    update: &LeafUpdate,
+) -> bool {
+    update.scheme == scheme
+        && update.old_root == *old_root
+        && update.new_leaf == *new_leaf
+        && update.scheme.verify_at_index::<H>(
+            &update.old_root,
+            &update.old_leaf,
+            update.index,
+            update.leaf_count,
+            &update.proof,
+        )
+        && update.scheme.verify_at_index::<H>(
+            &update.new_root,
+            &update.new_leaf,
+            update.index,
+            update.leaf_count,
+            &update.proof,
+        )
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::*;
+    use crate::test_util::{build_tree_with_scheme, leaves};
+    use crate::Sha256;
+
+    #[test]
+    fn test_update_matches_build() {
+        for num_leaves in 1..=17usize {
+            let mut leaves = leaves(num_leaves);
+            let mut merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+            for index in 0..num_leaves {
+                let old_root = merkle_tree.get_root();
+                let new_leaf =
+                    Scheme::LATEST.hash_leaf::<Sha256>(format!("new {}", index).as_ref());
+                let update = merkle_tree.update(index, new_leaf);
+                leaves[index] = new_leaf;
+
+                let rebuilt = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+                assert_eq!(merkle_tree.tree, rebuilt.tree);
+                assert_eq!(update.new_root, rebuilt.get_root());
+                assert_eq!(update.old_root, old_root);
+                assert!(verify_update::<Sha256>(
+                    &old_root,
+                    Scheme::LATEST,
+                    &new_leaf,
+                    &update
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_update_rejects_other_changes() {
+        let leaves = leaves(6);
+        let mut merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        let old_root = merkle_tree.get_root();
+        let new_leaf = Scheme::LATEST.hash_leaf::<Sha256>(b"new");
+        let update = merkle_tree.update(2, new_leaf);
+
+        // Not the root held by the verifier
+        assert!(!verify_update::<Sha256>(
+            &leaves[0],
+            Scheme::LATEST,
+            &new_leaf,
+            &update
+        ));
+        // Not the leaf the verifier wrote
+        assert!(!verify_update::<Sha256>(
+            &old_root,
+            Scheme::LATEST,
+            &leaves[2],
+            &update
+        ));
+        // New root claimed for a tree in which another leaf also changed
+        let mut other_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+        other_tree.update(2, new_leaf);
+        let mut forged = update.clone();
+        forged.new_root = other_tree.update(4, new_leaf).new_root;
+        assert!(!verify_update::<Sha256>(
+            &old_root,
+            Scheme::LATEST,
+            &new_leaf,
+            &forged
+        ));
+        // Update claimed at another index
+        let mut forged = update.clone();
+        forged.index = 3;
+        assert!(!verify_update::<Sha256>(
+            &old_root,
+            Scheme::LATEST,
+            &new_leaf,
+            &forged
+        ));
+    }
+
+    #[test]
+    fn test_verify_update_rejects_other_scheme() {
+        let new_leaf = Scheme::Sorted.hash_leaf::<Sha256>(b"new");
+        // A valid update of a tree built with another scheme than the verifier recorded
+        let (_, mut sorted_tree) = build_tree_with_scheme(6, Scheme::Sorted);
+        let old_root = sorted_tree.get_root();
+        let update = sorted_tree.update(2, new_leaf);
+        assert!(verify_update::<Sha256>(
+            &old_root,
+            Scheme::Sorted,
+            &new_leaf,
+            &update
+        ));
+        assert!(!verify_update::<Sha256>(
+            &old_root,
+            Scheme::LATEST,
+            &new_leaf,
+            &update
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+//! Build `verify-only` for a microcontroller target which has no std, so that neither the crate nor
+//! its dependencies can link std without this failing
+//!
+//! The target is not installed with the default toolchain, so the test is ignored unless run with
+//! `cargo test -p merkle_tree --test no_std -- --ignored`, as CI does.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+const NO_STD_TARGET: &str = "thumbv7em-none-eabihf";
+
+/// Return true if the standard library for a target is installed in the sysroot of the compiler
+fn target_installed(target: &str) -> bool {
+    let output = Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned()))
+        .args(["--print", "sysroot"])
+        .output()
+        .expect("failed to run rustc");
+    let sysroot = PathBuf::from(String::from_utf8(output.stdout).unwrap().trim());
+    sysroot.join("lib/rustlib").join(target).is_dir()
+}
+
+#[test]
+#[ignore = "needs the thumbv7em-none-eabihf target: `rustup target add thumbv7em-none-eabihf`"]
+fn test_verify_only_builds_without_std() {
+    assert!(
+        target_installed(NO_STD_TARGET),
+        "The no_std build needs the {0} target. Add it with `rustup target add {0}`",
+        NO_STD_TARGET
+    );
+    let status = Command::new(env!("CARGO"))
+        .args([
+            "build",
+            "--no-default-features",
+            "--features",
+            "verify-only,sha256",
+        ])
+        .args(["--target", NO_STD_TARGET, "--manifest-path"])
+        .arg(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("no_std"))
+        .status()
+        .expect("failed to run cargo");
+    assert!(
+        status.success(),
+        "verify-only failed to build for {}",
+        NO_STD_TARGET
+    );
+}
@@ -0,0 +1,110 @@
+/*
+ * C interface to the merkle_tree_ffi library, for verifying proofs returned by the server.
+ *
+ * Proofs are passed in the binary format of `Proof::to_bytes` and digests as 32 raw bytes. Every
+ * function returns a merkle_status, and writes its output only on MERKLE_OK. The server's /fetch
+ * sends proofs as JSON, whose fields callers must write out in that format themselves.
+ *
+ * A proof records the scheme and hash function it was made with, but these come from the server.
+ * Callers pin the scheme and hash function they recorded for a root, and merkle_verify_proof
+ * rejects a proof which records others rather than trust it.
+ *
+ * Link against libmerkle_tree_ffi.a or libmerkle_tree_ffi.so. The values of merkle_status and the
+ * layout of merkle_proof_info are stable and only ever extended.
+ */
+#ifndef MERKLE_TREE_H
+#define MERKLE_TREE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MERKLE_DIGEST_LEN 32
+#define MERKLE_SALT_LEN 32
+
+/* Scheme versions, as recorded in proofs */
+#define MERKLE_SCHEME_SORTED 0
+#define MERKLE_SCHEME_ORDERED 1
+#define MERKLE_SCHEME_DOMAIN_SEPARATED 2
+
+/* Hash function ids, as recorded in proofs. Only those enabled when the library was built verify */
+#define MERKLE_HASH_SHA256 0
+#define MERKLE_HASH_SHA512_256 1
+#define MERKLE_HASH_SHA3_256 2
+#define MERKLE_HASH_BLAKE3 3
+
+typedef enum merkle_status {
+    MERKLE_OK = 0,
+    /* A pointer argument was null */
+    MERKLE_ERR_NULL_POINTER = 1,
+    /* The proof bytes are not a proof in a known format version */
+    MERKLE_ERR_INVALID_ENCODING = 2,
+    /* No scheme has the given version */
+    MERKLE_ERR_UNKNOWN_SCHEME = 3,
+    /* The hash function id is unknown or was not enabled when the library was built */
+    MERKLE_ERR_UNSUPPORTED_HASHER = 4,
+    /* The proof's index is not less than its number of leaves */
+    MERKLE_ERR_INDEX_OUT_OF_RANGE = 5,
+    /* The proof does not take the leaf to the root */
+    MERKLE_ERR_INVALID_PROOF = 6,
+    /* A string is not a hex encoded digest */
+    MERKLE_ERR_INVALID_HEX = 7,
+    /* A chunk size was 0 */
+    MERKLE_ERR_INVALID_CHUNK_SIZE = 8,
+    /* The proof's scheme, hash function, index or number of leaves is not the one expected */
+    MERKLE_ERR_UNEXPECTED_PROOF = 9
+} merkle_status;
+
+/* Fields of a decoded proof, without its siblings */
+typedef struct merkle_proof_info {
+    uint8_t format_version;
+    uint8_t scheme_version;
+    uint8_t hash_id;
+    uint64_t index;
+    uint64_t leaf_count;
+    uint64_t sibling_count;
+} merkle_proof_info;
+
+/* Hash the contents of a file into a leaf with a scheme and hash function. data may be null if
+ * data_len is 0 */
+merkle_status merkle_hash_leaf(uint8_t scheme_version, uint8_t hash_id, const uint8_t *data,
+                               size_t data_len, uint8_t out[MERKLE_DIGEST_LEN]);
+
+/* Hash the contents of a file in chunks of chunk_size bytes into its leaf, for files stored with a
+ * chunk size. data may be null if data_len is 0 */
+merkle_status merkle_file_leaf(uint8_t hash_id, size_t chunk_size, const uint8_t *data,
+                               size_t data_len, uint8_t out[MERKLE_DIGEST_LEN]);
+
+/* Hash a salt into the leaf of a file, for collections stored with salts. The leaf is from
+ * merkle_hash_leaf or merkle_file_leaf */
+merkle_status merkle_salt_leaf(uint8_t hash_id, const uint8_t salt[MERKLE_SALT_LEN],
+                               const uint8_t leaf[MERKLE_DIGEST_LEN],
+                               uint8_t out[MERKLE_DIGEST_LEN]);
+
+/* Decode the fields of a proof */
+merkle_status merkle_proof_decode(const uint8_t *proof, size_t proof_len, merkle_proof_info *out);
+
+/* Verify that a proof takes a leaf to a root, for the file the caller asked for in the tree it
+ * recorded. The caller passes the scheme and hash function it pinned for the root, the index of the
+ * file it asked for and the number of files it stored, and a proof recording anything else fails
+ * with MERKLE_ERR_UNEXPECTED_PROOF */
+merkle_status merkle_verify_proof(const uint8_t *proof, size_t proof_len, uint8_t expected_scheme,
+                                  uint8_t expected_hash_id, uint64_t expected_index,
+                                  uint64_t expected_leaf_count,
+                                  const uint8_t root[MERKLE_DIGEST_LEN],
+                                  const uint8_t leaf[MERKLE_DIGEST_LEN]);
+
+/* Parse a digest from 64 hex characters, as digests are sent in JSON */
+merkle_status merkle_digest_from_hex(const char *hex, uint8_t out[MERKLE_DIGEST_LEN]);
+
+/* Static, null terminated description of a status. Never null */
+const char *merkle_status_message(int status);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MERKLE_TREE_H */
@@ -0,0 +1,323 @@
+//! C ABI for verifying proofs returned by the server, declared in `include/merkle_tree.h`
+//!
+//! Proofs are passed in the binary format of `Proof::to_bytes` and digests as 32 raw bytes. The JSON
+//! proofs from `/fetch` must be converted to that format by the caller.
+//! Every function returns a `MerkleStatus`, and writes its output only on `MERKLE_OK`.
+
+use merkle_tree::proof::PROOF_FORMAT_VERSION;
+use merkle_tree::{Digest, MerkleError, MerkleHasher, Proof, Salt, Scheme, DIGEST_LEN, SALT_LEN};
+use std::ffi::{c_char, c_int, CStr};
+use std::slice;
+
+/// Result of every function. The values are part of the stable ABI and are never reused
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkleStatus {
+    Ok = 0,
+    /// A pointer argument was null
+    NullPointer = 1,
+    /// The proof bytes are not a proof in a known format version
+    InvalidEncoding = 2,
+    /// No scheme has the given version
+    UnknownScheme = 3,
+    /// The hash function id is unknown or was not enabled when the library was built
+    UnsupportedHasher = 4,
+    /// The proof's index is not less than its number of leaves
+    IndexOutOfRange = 5,
+    /// The proof does not take the leaf to the root
+    InvalidProof = 6,
+    /// A string is not a hex encoded digest
+    InvalidHex = 7,
+    /// A chunk size was 0
+    InvalidChunkSize = 8,
+    /// The proof's scheme, hash function, index or number of leaves is not the one expected
+    UnexpectedProof = 9,
+}
+
+/// Fields of a decoded proof, without its siblings
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MerkleProofInfo {
+    pub format_version: u8,
+    pub scheme_version: u8,
+    pub hash_id: u8,
+    pub index: u64,
+    pub leaf_count: u64,
+    pub sibling_count: u64,
+}
+
+impl From<MerkleError> for MerkleStatus {
+    fn from(error: MerkleError) -> Self {
+        match error {
+            MerkleError::IndexOutOfRange { .. } => MerkleStatus::IndexOutOfRange,
+            MerkleError::UnexpectedHasher { .. } | MerkleError::UnexpectedScheme { .. } => {
+                MerkleStatus::UnexpectedProof
+            }
+            MerkleError::InvalidChunkSize => MerkleStatus::InvalidChunkSize,
+            _ => MerkleStatus::InvalidProof,
+        }
+    }
+}
+
+/// Call a function generic over the hash function with the enabled hash function of an id
+macro_rules! with_hasher {
+    ($hash_id:expr, $function:ident($($arg:expr),*)) => {
+        match $hash_id {
+            #[cfg(feature = "sha256")]
+            id if id == merkle_tree::hasher::Sha256::ID => {
+                $function::<merkle_tree::hasher::Sha256>($($arg),*)
+            }
+            #[cfg(feature = "sha512_256")]
+            id if id == merkle_tree::hasher::Sha512_256::ID => {
+                $function::<merkle_tree::hasher::Sha512_256>($($arg),*)
+            }
+            #[cfg(feature = "sha3")]
+            id if id == merkle_tree::hasher::Sha3_256::ID => {
+                $function::<merkle_tree::hasher::Sha3_256>($($arg),*)
+            }
+            #[cfg(feature = "blake3")]
+            id if id == merkle_tree::hasher::Blake3::ID => {
+                $function::<merkle_tree::hasher::Blake3>($($arg),*)
+            }
+            _ => Err(MerkleStatus::UnsupportedHasher),
+        }
+    };
+}
+
+fn hash_leaf<H: MerkleHasher>(scheme: Scheme, data: &[u8]) -> Result<Digest, MerkleStatus> {
+    Ok(scheme.hash_leaf::<H>(data))
+}
+
+fn file_leaf<H: MerkleHasher>(data: &[u8], chunk_size: usize) -> Result<Digest, MerkleStatus> {
+    Ok(merkle_tree::file_leaf::<H>(data, chunk_size)?)
+}
+
+fn salt_leaf<H: MerkleHasher>(salt: &Salt, leaf: &Digest) -> Result<Digest, MerkleStatus> {
+    Ok(merkle_tree::salt_leaf::<H>(salt, leaf))
+}
+
+fn verify<H: MerkleHasher>(
+    proof: &Proof,
+    scheme: Scheme,
+    root: &Digest,
+    leaf: &Digest,
+) -> Result<(), MerkleStatus> {
+    Ok(proof.try_verify::<H>(root, scheme, leaf)?)
+}
+
+/// Bytes of a pointer and length, where a null pointer is only allowed for no bytes
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], MerkleStatus> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(MerkleStatus::NullPointer),
+        (false, _) => Ok(slice::from_raw_parts(data, len)),
+    }
+}
+
+unsafe fn digest(data: *const u8) -> Result<Digest, MerkleStatus> {
+    if data.is_null() {
+        return Err(MerkleStatus::NullPointer);
+    }
+    Ok(Digest(*(data as *const [u8; DIGEST_LEN])))
+}
+
+unsafe fn decode_proof(proof: *const u8, proof_len: usize) -> Result<Proof, MerkleStatus> {
+    let bytes = bytes(proof, proof_len)?;
+    // Tell an unknown scheme apart from other bad input, as it may need a newer library
+    if let [PROOF_FORMAT_VERSION, scheme_version, ..] = *bytes {
+        Scheme::from_version(scheme_version).ok_or(MerkleStatus::UnknownScheme)?;
+    }
+    Proof::from_bytes(bytes).ok_or(MerkleStatus::InvalidEncoding)
+}
+
+/// Write a result to an output pointer, returning its status
+unsafe fn write_out<T>(result: Result<T, MerkleStatus>, out: *mut T) -> MerkleStatus {
+    if out.is_null() {
+        return MerkleStatus::NullPointer;
+    }
+    match result {
+        Ok(value) => {
+            out.write(value);
+            MerkleStatus::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+/// Hash the contents of a file into a leaf with a scheme and hash function
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes, or may be null if `data_len` is 0. `out` must
+/// point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_hash_leaf(
+    scheme_version: u8,
+    hash_id: u8,
+    data: *const u8,
+    data_len: usize,
+    out: *mut [u8; DIGEST_LEN],
+) -> MerkleStatus {
+    let result = (|| {
+        let scheme = Scheme::from_version(scheme_version).ok_or(MerkleStatus::UnknownScheme)?;
+        let data = bytes(data, data_len)?;
+        with_hasher!(hash_id, hash_leaf(scheme, data))
+    })();
+    write_out(result.map(|digest| digest.0), out)
+}
+
+/// Hash the contents of a file in chunks of `chunk_size` bytes into its leaf, for files stored with
+/// a chunk size
+///
+/// # Safety
+///
+/// `data` must point to `data_len` readable bytes, or may be null if `data_len` is 0. `out` must
+/// point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_file_leaf(
+    hash_id: u8,
+    chunk_size: usize,
+    data: *const u8,
+    data_len: usize,
+    out: *mut [u8; DIGEST_LEN],
+) -> MerkleStatus {
+    let result = (|| {
+        let data = bytes(data, data_len)?;
+        with_hasher!(hash_id, file_leaf(data, chunk_size))
+    })();
+    write_out(result.map(|digest| digest.0), out)
+}
+
+/// Hash a salt into the leaf of a file, for collections stored with salts. The leaf is from
+/// `merkle_hash_leaf` or `merkle_file_leaf`
+///
+/// # Safety
+///
+/// `salt` must point to 32 readable bytes, and `leaf` to 32 readable bytes. `out` must point to 32
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_salt_leaf(
+    hash_id: u8,
+    salt: *const u8,
+    leaf: *const u8,
+    out: *mut [u8; DIGEST_LEN],
+) -> MerkleStatus {
+    let result = (|| {
+        if salt.is_null() {
+            return Err(MerkleStatus::NullPointer);
+        }
+        let salt = Salt(*(salt as *const [u8; SALT_LEN]));
+        let leaf = digest(leaf)?;
+        with_hasher!(hash_id, salt_leaf(&salt, &leaf))
+    })();
+    write_out(result.map(|digest| digest.0), out)
+}
+
+/// Decode the fields of a proof
+///
+/// # Safety
+///
+/// `proof` must point to `proof_len` readable bytes. `out` must point to a writable
+/// `MerkleProofInfo`.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_proof_decode(
+    proof: *const u8,
+    proof_len: usize,
+    out: *mut MerkleProofInfo,
+) -> MerkleStatus {
+    let result = decode_proof(proof, proof_len).map(|proof| MerkleProofInfo {
+        format_version: proof.format_version,
+        scheme_version: proof.scheme.version(),
+        hash_id: proof.hash_id,
+        index: proof.index as u64,
+        leaf_count: proof.leaf_count as u64,
+        sibling_count: proof.siblings.len() as u64,
+    });
+    write_out(result, out)
+}
+
+/// Verify that a proof takes a leaf to a root, for the file the caller asked for in the tree it
+/// recorded
+///
+/// The caller pins the scheme and hash function it recorded for the root, the index of the file it
+/// asked for and the number of files it stored. A proof which records anything else is rejected
+/// with `MERKLE_ERR_UNEXPECTED_PROOF` rather than verified as the server describes it, as a weaker
+/// scheme such as sorted would not bind the leaf to its index.
+///
+/// # Safety
+///
+/// `proof` must point to `proof_len` readable bytes. `root` and `leaf` must each point to 32
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_verify_proof(
+    proof: *const u8,
+    proof_len: usize,
+    expected_scheme: u8,
+    expected_hash_id: u8,
+    expected_index: u64,
+    expected_leaf_count: u64,
+    root: *const u8,
+    leaf: *const u8,
+) -> MerkleStatus {
+    let result = (|| {
+        let scheme = Scheme::from_version(expected_scheme).ok_or(MerkleStatus::UnknownScheme)?;
+        let proof = decode_proof(proof, proof_len)?;
+        let root = digest(root)?;
+        let leaf = digest(leaf)?;
+        if proof.scheme != scheme
+            || proof.hash_id != expected_hash_id
+            || proof.index as u64 != expected_index
+            || proof.leaf_count as u64 != expected_leaf_count
+        {
+            return Err(MerkleStatus::UnexpectedProof);
+        }
+        with_hasher!(expected_hash_id, verify(&proof, scheme, &root, &leaf))
+    })();
+    match result {
+        Ok(()) => MerkleStatus::Ok,
+        Err(status) => status,
+    }
+}
+
+/// Parse a digest from 64 hex characters, as digests are sent in JSON
+///
+/// # Safety
+///
+/// `hex` must point to a null terminated string. `out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_digest_from_hex(
+    hex: *const c_char,
+    out: *mut [u8; DIGEST_LEN],
+) -> MerkleStatus {
+    let result = if hex.is_null() {
+        Err(MerkleStatus::NullPointer)
+    } else {
+        CStr::from_ptr(hex)
+            .to_str()
+            .ok()
+            .and_then(Digest::from_hex)
+            .map(|digest| digest.0)
+            .ok_or(MerkleStatus::InvalidHex)
+    };
+    write_out(result, out)
+}
+
+/// Static, null terminated description of a status. Takes the status as an int so that any value
+/// from C is safe to pass
+#[no_mangle]
+pub extern "C" fn merkle_status_message(status: c_int) -> *const c_char {
+    let message: &'static CStr = match status {
+        0 => c"Ok",
+        1 => c"A pointer argument was null",
+        2 => c"Bytes are not a proof in a known format",
+        3 => c"Unknown scheme version",
+        4 => c"Unknown or disabled hash function",
+        5 => c"Proof index is not less than its number of leaves",
+        6 => c"Proof does not validate the leaf against the root",
+        7 => c"String is not a hex encoded digest",
+        8 => c"Chunk size must be greater than 0",
+        9 => c"Proof is not for the expected scheme, hash function, index or number of leaves",
+        _ => c"Unknown status",
+    };
+    message.as_ptr()
+}
@@ -0,0 +1,140 @@
+//! Build the C test program against the static library and run it on proofs built here
+
+use merkle_tree::{salt_leaf, ChunkTree, Digest, MerkleHasher, MerkleTree, Salt, Scheme, Sha256};
+use merkle_tree_ffi::{merkle_file_leaf, merkle_salt_leaf, merkle_verify_proof, MerkleStatus};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Directory the static library is built into for tests, which also holds this test
+fn library_dir() -> PathBuf {
+    let test_exe = std::env::current_exe().unwrap();
+    test_exe.parent().unwrap().to_path_buf()
+}
+
+fn compile_c_test() -> PathBuf {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let output = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("verify_c");
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_owned()))
+        .arg(manifest_dir.join("tests/verify.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(library_dir().join("libmerkle_tree_ffi.a"))
+        .args(["-lpthread", "-ldl", "-lm", "-Wall", "-Werror", "-o"])
+        .arg(&output)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "C test program failed to compile");
+    output
+}
+
+#[test]
+fn test_c_program() {
+    let c_test = compile_c_test();
+    let files: Vec<String> = (0..5).map(|x| format!("file {}", x)).collect();
+    for scheme in [Scheme::Sorted, Scheme::Ordered, Scheme::DomainSeparated] {
+        let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(
+            &scheme.hash_leaves::<Sha256, _>(&files),
+            scheme,
+        );
+        for index in [0, 3, 4] {
+            let proof = merkle_tree.prove_leaf(index).to_bytes();
+            let output = Command::new(&c_test)
+                .arg(merkle_tree.get_root().to_hex())
+                .arg(
+                    proof
+                        .iter()
+                        .map(|byte| format!("{:02x}", byte))
+                        .collect::<String>(),
+                )
+                .arg(&files[index])
+                .arg(index.to_string())
+                .arg(files.len().to_string())
+                .arg(scheme.version().to_string())
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "C test program failed for {:?} index {}:\n{}",
+                scheme,
+                index,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_salted_chunked_leaves() {
+    let files: Vec<Vec<u8>> = (0..5).map(|x| vec![x; 100 * x as usize]).collect();
+    let salts: Vec<Salt> = (0..files.len() as u8).map(|x| Salt([x; 32])).collect();
+    let leaves: Vec<Digest> = files
+        .iter()
+        .zip(&salts)
+        .map(|(file, salt)| {
+            salt_leaf::<Sha256>(salt, &ChunkTree::<Sha256>::build(file, 64).file_leaf())
+        })
+        .collect();
+    let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::LATEST);
+    for (index, file) in files.iter().enumerate() {
+        let mut file_leaf = [0; 32];
+        let mut leaf = [0; 32];
+        let proof = merkle_tree.prove_leaf(index).to_bytes();
+        unsafe {
+            assert_eq!(
+                merkle_file_leaf(Sha256::ID, 64, file.as_ptr(), file.len(), &mut file_leaf),
+                MerkleStatus::Ok
+            );
+            assert_eq!(
+                merkle_salt_leaf(
+                    Sha256::ID,
+                    salts[index].0.as_ptr(),
+                    file_leaf.as_ptr(),
+                    &mut leaf
+                ),
+                MerkleStatus::Ok
+            );
+            assert_eq!(
+                merkle_verify_proof(
+                    proof.as_ptr(),
+                    proof.len(),
+                    Scheme::LATEST.version(),
+                    Sha256::ID,
+                    index as u64,
+                    files.len() as u64,
+                    merkle_tree.get_root().0.as_ptr(),
+                    leaf.as_ptr()
+                ),
+                MerkleStatus::Ok
+            );
+        }
+        assert_eq!(Digest(leaf), leaves[index]);
+    }
+}
+
+#[test]
+fn test_proof_for_other_scheme_is_rejected() {
+    // A valid proof from a tree built with the sorted scheme, which does not bind leaves to their
+    // indices, is rejected when the caller pinned the domain separated scheme
+    let leaves: Vec<Digest> = (0..5)
+        .map(|x| Scheme::Sorted.hash_leaf::<Sha256>(format!("file {}", x).as_bytes()))
+        .collect();
+    let merkle_tree = MerkleTree::<Sha256>::build_with_scheme(&leaves, Scheme::Sorted);
+    let proof = merkle_tree.prove_leaf(2).to_bytes();
+    let verify = |scheme: Scheme| unsafe {
+        merkle_verify_proof(
+            proof.as_ptr(),
+            proof.len(),
+            scheme.version(),
+            Sha256::ID,
+            2,
+            5,
+            merkle_tree.get_root().0.as_ptr(),
+            leaves[2].0.as_ptr(),
+        )
+    };
+    assert_eq!(verify(Scheme::Sorted), MerkleStatus::Ok);
+    assert_eq!(
+        verify(Scheme::DomainSeparated),
+        MerkleStatus::UnexpectedProof
+    );
+}
@@ -0,0 +1,168 @@
+/*
+ * Exercises the C interface against a proof built by the Rust test which runs this program.
+ *
+ * Usage: verify <root hex> <proof hex> <file contents> <file index> <number of files> <scheme>
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "merkle_tree.h"
+
+static int failures = 0;
+
+#define CHECK_STATUS(call, expected)                                                       \
+    do {                                                                                   \
+        merkle_status status = (call);                                                     \
+        if (status != (expected)) {                                                        \
+            fprintf(stderr, "%s:%d: %s returned %d (%s), expected %d\n", __FILE__, __LINE__, \
+                    #call, status, merkle_status_message(status), (expected));             \
+            failures++;                                                                    \
+        }                                                                                  \
+    } while (0)
+
+#define CHECK(condition)                                                                   \
+    do {                                                                                   \
+        if (!(condition)) {                                                                \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition);  \
+            failures++;                                                                    \
+        }                                                                                  \
+    } while (0)
+
+/* Decode an even length hex string into a newly allocated buffer */
+static uint8_t *decode_hex(const char *hex, size_t *len) {
+    size_t hex_len = strlen(hex);
+    uint8_t *bytes = malloc(hex_len / 2 + 1);
+    for (size_t i = 0; i < hex_len / 2; i++) {
+        unsigned int byte;
+        sscanf(hex + 2 * i, "%2x", &byte);
+        bytes[i] = (uint8_t)byte;
+    }
+    *len = hex_len / 2;
+    return bytes;
+}
+
+int main(int argc, char **argv) {
+    if (argc != 7) {
+        fprintf(stderr,
+                "usage: %s <root hex> <proof hex> <file contents> <index> <leaf count> <scheme>\n",
+                argv[0]);
+        return 2;
+    }
+    const char *file = argv[3];
+    uint64_t index = strtoull(argv[4], NULL, 10);
+    uint64_t leaf_count = strtoull(argv[5], NULL, 10);
+    uint8_t scheme = (uint8_t)strtoul(argv[6], NULL, 10);
+
+    uint8_t root[MERKLE_DIGEST_LEN];
+    CHECK_STATUS(merkle_digest_from_hex(argv[1], root), MERKLE_OK);
+    size_t proof_len;
+    uint8_t *proof = decode_hex(argv[2], &proof_len);
+
+    /* The proof describes itself */
+    merkle_proof_info info;
+    CHECK_STATUS(merkle_proof_decode(proof, proof_len, &info), MERKLE_OK);
+    CHECK(info.format_version == 1);
+    CHECK(info.scheme_version == scheme);
+    CHECK(info.hash_id == MERKLE_HASH_SHA256);
+    CHECK(info.index == index);
+    CHECK(info.leaf_count == leaf_count);
+    CHECK(proof_len == 3 + 1 + 1 + info.sibling_count * MERKLE_DIGEST_LEN);
+
+    /* The file verifies against the root, and other contents do not */
+    uint8_t leaf[MERKLE_DIGEST_LEN];
+    CHECK_STATUS(merkle_hash_leaf(scheme, MERKLE_HASH_SHA256, (const uint8_t *)file, strlen(file),
+                                  leaf),
+                 MERKLE_OK);
+#define VERIFY(proof, proof_len, leaf) \
+    merkle_verify_proof(proof, proof_len, scheme, MERKLE_HASH_SHA256, index, leaf_count, root, leaf)
+    CHECK_STATUS(VERIFY(proof, proof_len, leaf), MERKLE_OK);
+    uint8_t other_leaf[MERKLE_DIGEST_LEN];
+    CHECK_STATUS(merkle_hash_leaf(scheme, MERKLE_HASH_SHA256, (const uint8_t *)"other", 5,
+                                  other_leaf),
+                 MERKLE_OK);
+    CHECK_STATUS(VERIFY(proof, proof_len, other_leaf), MERKLE_ERR_INVALID_PROOF);
+
+    /* The proof must be for the pinned scheme, hash function, index and number of leaves */
+    uint8_t other_scheme =
+        scheme == MERKLE_SCHEME_SORTED ? MERKLE_SCHEME_ORDERED : MERKLE_SCHEME_SORTED;
+    CHECK_STATUS(merkle_verify_proof(proof, proof_len, other_scheme, MERKLE_HASH_SHA256, index,
+                                     leaf_count, root, leaf),
+                 MERKLE_ERR_UNEXPECTED_PROOF);
+    CHECK_STATUS(merkle_verify_proof(proof, proof_len, scheme, MERKLE_HASH_BLAKE3, index,
+                                     leaf_count, root, leaf),
+                 MERKLE_ERR_UNEXPECTED_PROOF);
+    CHECK_STATUS(merkle_verify_proof(proof, proof_len, scheme, MERKLE_HASH_SHA256, index + 1,
+                                     leaf_count, root, leaf),
+                 MERKLE_ERR_UNEXPECTED_PROOF);
+    CHECK_STATUS(merkle_verify_proof(proof, proof_len, scheme, MERKLE_HASH_SHA256, index,
+                                     leaf_count + 1, root, leaf),
+                 MERKLE_ERR_UNEXPECTED_PROOF);
+    CHECK_STATUS(merkle_verify_proof(proof, proof_len, 0xee, MERKLE_HASH_SHA256, index, leaf_count,
+                                     root, leaf),
+                 MERKLE_ERR_UNKNOWN_SCHEME);
+
+    /* A changed sibling fails */
+    uint8_t *tampered = malloc(proof_len);
+    memcpy(tampered, proof, proof_len);
+    tampered[proof_len - 1] ^= 1;
+    CHECK_STATUS(VERIFY(tampered, proof_len, leaf), MERKLE_ERR_INVALID_PROOF);
+
+    /* The same proof claiming another scheme is rejected rather than verified under it */
+    memcpy(tampered, proof, proof_len);
+    tampered[1] = other_scheme;
+    CHECK_STATUS(VERIFY(tampered, proof_len, leaf), MERKLE_ERR_UNEXPECTED_PROOF);
+
+    /* Each kind of bad input has its own status */
+    CHECK_STATUS(VERIFY(NULL, proof_len, leaf), MERKLE_ERR_NULL_POINTER);
+    CHECK_STATUS(VERIFY(proof, proof_len, NULL), MERKLE_ERR_NULL_POINTER);
+    CHECK_STATUS(VERIFY(proof, proof_len - 1, leaf), MERKLE_ERR_INVALID_ENCODING);
+    memcpy(tampered, proof, proof_len);
+    tampered[0] = 0xee;
+    CHECK_STATUS(merkle_proof_decode(tampered, proof_len, &info), MERKLE_ERR_INVALID_ENCODING);
+    memcpy(tampered, proof, proof_len);
+    tampered[1] = 0xee;
+    CHECK_STATUS(merkle_proof_decode(tampered, proof_len, &info), MERKLE_ERR_UNKNOWN_SCHEME);
+    memcpy(tampered, proof, proof_len);
+    tampered[2] = 0xee;
+    CHECK_STATUS(VERIFY(tampered, proof_len, leaf), MERKLE_ERR_UNEXPECTED_PROOF);
+    CHECK_STATUS(merkle_verify_proof(proof, proof_len, scheme, 0xee, index, leaf_count, root, leaf),
+                 MERKLE_ERR_UNEXPECTED_PROOF);
+    /* A hash function which matches but was not enabled in the library */
+    tampered[2] = MERKLE_HASH_BLAKE3;
+    CHECK_STATUS(merkle_verify_proof(tampered, proof_len, scheme, MERKLE_HASH_BLAKE3, index,
+                                     leaf_count, root, leaf),
+                 MERKLE_ERR_UNSUPPORTED_HASHER);
+    CHECK_STATUS(merkle_hash_leaf(0xee, MERKLE_HASH_SHA256, NULL, 0, leaf),
+                 MERKLE_ERR_UNKNOWN_SCHEME);
+    CHECK_STATUS(merkle_hash_leaf(MERKLE_SCHEME_DOMAIN_SEPARATED, MERKLE_HASH_SHA256, NULL, 1, leaf),
+                 MERKLE_ERR_NULL_POINTER);
+    CHECK_STATUS(merkle_digest_from_hex("5feceb66", root), MERKLE_ERR_INVALID_HEX);
+
+    /* Chunked and salted leaves differ from the plain leaf */
+    uint8_t file_leaf[MERKLE_DIGEST_LEN];
+    CHECK_STATUS(merkle_file_leaf(MERKLE_HASH_SHA256, 4, (const uint8_t *)file, strlen(file),
+                                  file_leaf),
+                 MERKLE_OK);
+    CHECK(memcmp(file_leaf, leaf, MERKLE_DIGEST_LEN) != 0);
+    uint8_t salt[MERKLE_SALT_LEN] = {1};
+    uint8_t salted_leaf[MERKLE_DIGEST_LEN];
+    CHECK_STATUS(merkle_salt_leaf(MERKLE_HASH_SHA256, salt, file_leaf, salted_leaf), MERKLE_OK);
+    CHECK(memcmp(salted_leaf, file_leaf, MERKLE_DIGEST_LEN) != 0);
+    CHECK_STATUS(merkle_file_leaf(MERKLE_HASH_SHA256, 0, (const uint8_t *)file, strlen(file),
+                                  file_leaf),
+                 MERKLE_ERR_INVALID_CHUNK_SIZE);
+    CHECK_STATUS(merkle_salt_leaf(MERKLE_HASH_SHA256, NULL, file_leaf, salted_leaf),
+                 MERKLE_ERR_NULL_POINTER);
+
+    CHECK(strcmp(merkle_status_message(MERKLE_OK), "Ok") == 0);
+    CHECK(strcmp(merkle_status_message(1000), "Unknown status") == 0);
+
+    free(tampered);
+    free(proof);
+    if (failures > 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    return 0;
+}
@@ -0,0 +1,259 @@
+//! Python bindings to `merkle_tree`, built as the `merkle_tree` extension module with maturin
+//!
+//! Digests are passed as 32 byte `bytes` and schemes by name: "sorted", "ordered" or
+//! "domain_separated". Every tree is built with SHA-256, so roots match those of the Rust crate and
+//! the server byte for byte.
+
+use merkle_tree::{
+    Digest, MerkleError, MerkleTree, Proof, Salt, Scheme, Sha256, DIGEST_LEN, SALT_LEN,
+};
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+fn digest(bytes: &[u8]) -> PyResult<Digest> {
+    bytes.try_into().map(Digest).map_err(|_| {
+        PyValueError::new_err(format!(
+            "Digest must be {} bytes, not {}",
+            DIGEST_LEN,
+            bytes.len()
+        ))
+    })
+}
+
+fn digests(items: &[Vec<u8>]) -> PyResult<Vec<Digest>> {
+    items.iter().map(|item| digest(item)).collect()
+}
+
+fn digest_bytes<'py>(py: Python<'py>, digest: &Digest) -> Bound<'py, PyBytes> {
+    PyBytes::new_bound(py, digest.as_bytes())
+}
+
+fn scheme(name: &str) -> PyResult<Scheme> {
+    match name {
+        "sorted" => Ok(Scheme::Sorted),
+        "ordered" => Ok(Scheme::Ordered),
+        "domain_separated" => Ok(Scheme::DomainSeparated),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown scheme {}. Expected sorted, ordered or domain_separated",
+            name
+        ))),
+    }
+}
+
+fn scheme_name(scheme: Scheme) -> &'static str {
+    match scheme {
+        Scheme::Sorted => "sorted",
+        Scheme::Ordered => "ordered",
+        Scheme::DomainSeparated => "domain_separated",
+    }
+}
+
+/// An index out of range is an IndexError, as for a list. Anything else is a ValueError
+fn merkle_error(error: MerkleError) -> PyErr {
+    match error {
+        MerkleError::IndexOutOfRange { .. } => PyIndexError::new_err(error.to_string()),
+        _ => PyValueError::new_err(error.to_string()),
+    }
+}
+
+/// Functions and classes exposed to Python
+///
+/// pyo3 generates a wrapper next to each of these, which for a function returning `PyResult`
+/// converts the `PyErr` into itself. The wrappers are not inside the functions, so the lint can only
+/// be allowed on the module holding them.
+#[allow(clippy::useless_conversion)]
+mod python {
+    use super::*;
+
+    /// SHA-256 hash of some data
+    #[pyfunction]
+    pub(super) fn hash<'py>(py: Python<'py>, data: &[u8]) -> Bound<'py, PyBytes> {
+        digest_bytes(py, &merkle_tree::hash(data))
+    }
+
+    /// Hash the contents of a file into a leaf with a scheme
+    #[pyfunction]
+    #[pyo3(signature = (data, scheme = "domain_separated"))]
+    pub(super) fn hash_leaf<'py>(
+        py: Python<'py>,
+        data: &[u8],
+        scheme: &str,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        Ok(digest_bytes(
+            py,
+            &self::scheme(scheme)?.hash_leaf::<Sha256>(data),
+        ))
+    }
+
+    /// Hash the contents of a file in chunks of `chunk_size` bytes into its leaf, for files stored with
+    /// a chunk size. Raises ValueError if the chunk size is 0
+    #[pyfunction]
+    pub(super) fn file_leaf<'py>(
+        py: Python<'py>,
+        data: &[u8],
+        chunk_size: usize,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let leaf = merkle_tree::file_leaf::<Sha256>(data, chunk_size).map_err(merkle_error)?;
+        Ok(digest_bytes(py, &leaf))
+    }
+
+    /// Hash a 32 byte salt into the leaf of a file from `hash_leaf` or `file_leaf`, for collections
+    /// stored with salts
+    #[pyfunction]
+    pub(super) fn salt_leaf<'py>(
+        py: Python<'py>,
+        salt: &[u8],
+        leaf: &[u8],
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let salt = salt.try_into().map(Salt).map_err(|_| {
+            PyValueError::new_err(format!(
+                "Salt must be {} bytes, not {}",
+                SALT_LEN,
+                salt.len()
+            ))
+        })?;
+        Ok(digest_bytes(
+            py,
+            &merkle_tree::salt_leaf::<Sha256>(&salt, &digest(leaf)?),
+        ))
+    }
+
+    /// Return true if a proof from `MerkleTree.prove` validates a leaf in a tree built with the
+    /// sorted scheme, which does not check the leaf's index
+    #[pyfunction]
+    pub(super) fn verify(root: &[u8], leaf: &[u8], proof: Vec<Vec<u8>>) -> PyResult<bool> {
+        Ok(merkle_tree::verify::<Sha256>(
+            &digest(root)?,
+            &digest(leaf)?,
+            &digests(&proof)?,
+        ))
+    }
+
+    /// Return true if a proof from `MerkleTree.prove` validates a leaf at exactly an index of a tree
+    /// with `leaf_count` leaves
+    #[pyfunction]
+    #[pyo3(signature = (root, leaf, index, leaf_count, proof, scheme = "domain_separated"))]
+    pub(super) fn verify_at_index(
+        root: &[u8],
+        leaf: &[u8],
+        index: usize,
+        leaf_count: usize,
+        proof: Vec<Vec<u8>>,
+        scheme: &str,
+    ) -> PyResult<bool> {
+        Ok(self::scheme(scheme)?.verify_at_index::<Sha256>(
+            &digest(root)?,
+            &digest(leaf)?,
+            index,
+            leaf_count,
+            &digests(&proof)?,
+        ))
+    }
+
+    /// Return true if a proof in the binary format of `MerkleTree.prove_leaf` validates a leaf at
+    /// exactly an index of a SHA-256 tree with `leaf_count` leaves built with a scheme. The proof's
+    /// own scheme, hash function, index and number of leaves must match, rather than being trusted.
+    /// Raises ValueError if the bytes are not a proof
+    #[pyfunction]
+    #[pyo3(signature = (proof, root, leaf, index, leaf_count, scheme = "domain_separated"))]
+    pub(super) fn verify_proof(
+        proof: &[u8],
+        root: &[u8],
+        leaf: &[u8],
+        index: usize,
+        leaf_count: usize,
+        scheme: &str,
+    ) -> PyResult<bool> {
+        let proof = Proof::from_bytes(proof)
+            .ok_or_else(|| PyValueError::new_err("Bytes are not a proof in a known format"))?;
+        Ok(proof.index == index
+            && proof.leaf_count == leaf_count
+            && proof.verify::<Sha256>(&digest(root)?, self::scheme(scheme)?, &digest(leaf)?))
+    }
+
+    /// Merkle tree over 32 byte leaf hashes, built with SHA-256
+    ///
+    /// The scheme defaults to "domain_separated", as do `hash_leaf` and `verify_at_index`, so that
+    /// leaves, trees and proofs made with the defaults all agree.
+    #[pyclass(name = "MerkleTree", module = "merkle_tree", frozen)]
+    pub(super) struct PyMerkleTree {
+        tree: MerkleTree<Sha256>,
+    }
+
+    #[pymethods]
+    impl PyMerkleTree {
+        #[new]
+        #[pyo3(signature = (leaves, scheme = "domain_separated"))]
+        fn new(leaves: Vec<Vec<u8>>, scheme: &str) -> PyResult<Self> {
+            let tree = MerkleTree::try_build_with_scheme(&digests(&leaves)?, self::scheme(scheme)?)
+                .map_err(merkle_error)?;
+            Ok(PyMerkleTree { tree })
+        }
+
+        #[getter]
+        fn root<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+            digest_bytes(py, &self.tree.get_root())
+        }
+
+        #[getter]
+        fn num_leaves(&self) -> usize {
+            self.tree.num_leaves
+        }
+
+        #[getter]
+        fn scheme(&self) -> &'static str {
+            scheme_name(self.tree.scheme)
+        }
+
+        /// Every node, row by row from the leaves up to the root
+        #[getter]
+        fn rows<'py>(&self, py: Python<'py>) -> Vec<Vec<Bound<'py, PyBytes>>> {
+            self.tree
+                .tree
+                .iter()
+                .map(|row| row.iter().map(|node| digest_bytes(py, node)).collect())
+                .collect()
+        }
+
+        /// Siblings on the path from the leaf at an index to the root
+        fn prove<'py>(&self, py: Python<'py>, index: usize) -> PyResult<Vec<Bound<'py, PyBytes>>> {
+            let proof = self.tree.try_prove(index).map_err(merkle_error)?;
+            Ok(proof.iter().map(|node| digest_bytes(py, node)).collect())
+        }
+
+        /// Self-describing proof for the leaf at an index, in the binary format the Rust crate uses
+        fn prove_leaf<'py>(&self, py: Python<'py>, index: usize) -> PyResult<Bound<'py, PyBytes>> {
+            let proof = self.tree.try_prove_leaf(index).map_err(merkle_error)?;
+            Ok(PyBytes::new_bound(py, &proof.to_bytes()))
+        }
+
+        fn __len__(&self) -> usize {
+            self.tree.num_leaves
+        }
+
+        fn __repr__(&self) -> String {
+            format!(
+                "MerkleTree(num_leaves={}, scheme='{}', root='{}')",
+                self.tree.num_leaves,
+                self.scheme(),
+                self.tree.get_root()
+            )
+        }
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "merkle_tree")]
+fn merkle_tree_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    use python::*;
+    module.add_class::<PyMerkleTree>()?;
+    module.add_function(wrap_pyfunction!(hash, module)?)?;
+    module.add_function(wrap_pyfunction!(hash_leaf, module)?)?;
+    module.add_function(wrap_pyfunction!(file_leaf, module)?)?;
+    module.add_function(wrap_pyfunction!(salt_leaf, module)?)?;
+    module.add_function(wrap_pyfunction!(verify, module)?)?;
+    module.add_function(wrap_pyfunction!(verify_at_index, module)?)?;
+    module.add_function(wrap_pyfunction!(verify_proof, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Run the Python tests against the extension module built for these tests
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn test_python_module() {
+    // The extension is built next to this test, and Python imports it as merkle_tree
+    let test_exe = std::env::current_exe().unwrap();
+    let library = test_exe
+        .parent()
+        .unwrap()
+        .join(format!("{}merkle_tree_py{}", DLL_PREFIX, DLL_SUFFIX));
+    let module_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("python");
+    fs::create_dir_all(&module_dir).unwrap();
+    fs::copy(&library, module_dir.join("merkle_tree.so")).unwrap();
+
+    // The interpreter pyo3 was built against
+    let python = std::env::var("PYO3_PYTHON").unwrap_or_else(|_| "python3".to_owned());
+    let output = Command::new(python)
+        .arg(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/test_merkle_tree.py"))
+        .env("PYTHONPATH", &module_dir)
+        .output()
+        .expect("failed to run Python");
+    assert!(
+        output.status.success(),
+        "Python tests failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,207 @@
+"""Tests of the merkle_tree extension against the test vectors in merkle_tree/src/lib.rs"""
+
+import hashlib
+import unittest
+
+import merkle_tree
+
+
+def hashes(count):
+    return [merkle_tree.hash(str(x).encode()) for x in range(count)]
+
+
+class TestMerkleTree(unittest.TestCase):
+    def test_build_and_verify(self):
+        leaves = hashes(4)
+        tree = merkle_tree.MerkleTree(leaves, scheme="sorted")
+        # Data generated manually
+        expected_rows = [
+            [
+                "5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9",
+                "6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b",
+                "d4735e3a265e16eee03f59718b9b5d03019c07d8b6c51f90da3a666eec13ab35",
+                "4e07408562bedb8b60ce05c1decfe3ad16b72230967de01f640b7e4729b49fce",
+            ],
+            [
+                "dbdbf4fb684471f421fb255100e433c77fd1aac71c7a3739e9897168aec67ec1",
+                "70311d9d203b2d7e4ff70d7fce219f82a4fcf73a110dc80187dfefb7c6e4bb87",
+            ],
+            ["a48572b1744e5e3f3473c9eaa91f73be774712d2b207c34eb537023af0ec6528"],
+        ]
+        self.assertEqual([[node.hex() for node in row] for row in tree.rows], expected_rows)
+        self.assertEqual(tree.root.hex(), expected_rows[2][0])
+        for index, leaf in enumerate(leaves):
+            self.assertTrue(merkle_tree.verify(tree.root, leaf, tree.prove(index)))
+
+    def test_prove(self):
+        tree = merkle_tree.MerkleTree(hashes(4))
+        rows = tree.rows
+        self.assertEqual(tree.prove(0), [rows[0][1], rows[1][1]])
+        self.assertEqual(tree.prove(1), [rows[0][0], rows[1][1]])
+        self.assertEqual(tree.prove(2), [rows[0][3], rows[1][0]])
+        self.assertEqual(tree.prove(3), [rows[0][2], rows[1][0]])
+
+    def test_prove_out_of_range(self):
+        tree = merkle_tree.MerkleTree(hashes(4))
+        with self.assertRaisesRegex(IndexError, "Index 4 too large. Tree contains 4 leaves"):
+            tree.prove(4)
+
+    def test_bad_input(self):
+        with self.assertRaisesRegex(ValueError, "Number of leaves must be greater than 0"):
+            merkle_tree.MerkleTree([])
+        with self.assertRaises(ValueError):
+            merkle_tree.MerkleTree([b"not a digest"])
+        with self.assertRaises(ValueError):
+            merkle_tree.MerkleTree(hashes(2), scheme="unknown")
+
+    def test_build_unpaired_leaf_is_promoted(self):
+        leaves = hashes(3)
+        tree = merkle_tree.MerkleTree(leaves)
+        self.assertEqual(len(tree.rows), 3)
+        self.assertEqual(tree.rows[1][1], leaves[2])
+        self.assertEqual(tree.prove(2), [tree.rows[1][0]])
+
+    def test_prove_and_verify_any_leaf_count(self):
+        for num_leaves in range(1, 34):
+            leaves = hashes(num_leaves)
+            tree = merkle_tree.MerkleTree(leaves, scheme="sorted")
+            self.assertEqual(len(tree), num_leaves)
+            for index, leaf in enumerate(leaves):
+                proof = tree.prove(index)
+                self.assertTrue(merkle_tree.verify(tree.root, leaf, proof))
+                self.assertFalse(merkle_tree.verify(tree.root, merkle_tree.hash(b"not a leaf"), proof))
+
+    def test_single_leaf(self):
+        leaves = [merkle_tree.hash(b"0")]
+        tree = merkle_tree.MerkleTree(leaves)
+        self.assertEqual(tree.root, leaves[0])
+        self.assertEqual(tree.prove(0), [])
+
+    def test_ordered_build(self):
+        leaves = hashes(4)
+        tree = merkle_tree.MerkleTree(leaves, scheme="ordered")
+
+        # Nodes are the hash of the hex text of the left child followed by the right child
+        def node(left, right):
+            return hashlib.sha256((left.hex() + right.hex()).encode()).digest()
+
+        left = node(leaves[0], leaves[1])
+        right = node(leaves[2], leaves[3])
+        self.assertEqual(tree.rows[1], [left, right])
+        self.assertEqual(tree.root, node(left, right))
+        self.assertNotEqual(tree.root, merkle_tree.MerkleTree(leaves, scheme="sorted").root)
+
+    def test_verify_at_index(self):
+        for num_leaves in range(1, 34):
+            leaves = hashes(num_leaves)
+            tree = merkle_tree.MerkleTree(leaves, scheme="ordered")
+            for index, leaf in enumerate(leaves):
+                proof = tree.prove(index)
+                self.assertTrue(
+                    merkle_tree.verify_at_index(
+                        tree.root, leaf, index, num_leaves, proof, scheme="ordered"
+                    )
+                )
+                self.assertFalse(
+                    merkle_tree.verify_at_index(
+                        tree.root, leaf, num_leaves, num_leaves, proof, scheme="ordered"
+                    )
+                )
+                if num_leaves > 1:
+                    other_index = (index + 1) % num_leaves
+                    self.assertFalse(
+                        merkle_tree.verify_at_index(
+                            tree.root, leaf, other_index, num_leaves, proof, scheme="ordered"
+                        )
+                    )
+
+    def test_domain_separated_build_and_verify(self):
+        leaves = [merkle_tree.hash_leaf(str(x).encode()) for x in range(4)]
+        # Leaves are prefixed with 0x00
+        self.assertEqual(leaves[0], hashlib.sha256(b"\x000").digest())
+        self.assertEqual(leaves[0], merkle_tree.hash_leaf(b"0", scheme="domain_separated"))
+        self.assertEqual(merkle_tree.hash_leaf(b"0", scheme="sorted"), merkle_tree.hash(b"0"))
+
+        tree = merkle_tree.MerkleTree(leaves, scheme="domain_separated")
+
+        # Nodes are the hash of 0x01 followed by the raw bytes of both children
+        def node(left, right):
+            return hashlib.sha256(b"\x01" + left + right).digest()
+
+        root = node(node(leaves[0], leaves[1]), node(leaves[2], leaves[3]))
+        self.assertEqual(tree.root, root)
+        for index, leaf in enumerate(leaves):
+            proof = tree.prove(index)
+            self.assertTrue(
+                merkle_tree.verify_at_index(root, leaf, index, 4, proof, scheme="domain_separated")
+            )
+            self.assertFalse(
+                merkle_tree.verify_at_index(root, leaf, index, 4, proof, scheme="ordered")
+            )
+
+    def test_binary_proof(self):
+        leaves = [merkle_tree.hash_leaf(str(x).encode()) for x in range(5)]
+        tree = merkle_tree.MerkleTree(leaves, scheme="domain_separated")
+        proof = tree.prove_leaf(3)
+        # Format version, scheme version, hash function id, index and number of leaves
+        self.assertEqual(proof[:5], bytes([1, 2, 0, 3, 5]))
+        self.assertEqual(proof[5:], b"".join(tree.prove(3)))
+        self.assertTrue(merkle_tree.verify_proof(proof, tree.root, leaves[3], 3, 5))
+        self.assertFalse(merkle_tree.verify_proof(proof, tree.root, leaves[2], 3, 5))
+        with self.assertRaises(ValueError):
+            merkle_tree.verify_proof(proof[:-1], tree.root, leaves[3], 3, 5)
+
+        # The proof must be for the caller's index, number of leaves and scheme
+        self.assertFalse(merkle_tree.verify_proof(proof, tree.root, leaves[3], 2, 5))
+        self.assertFalse(merkle_tree.verify_proof(proof, tree.root, leaves[3], 3, 6))
+        self.assertFalse(
+            merkle_tree.verify_proof(proof, tree.root, leaves[3], 3, 5, scheme="ordered")
+        )
+        # Re-encoded as a sorted proof, which does not bind the leaf to its index
+        sorted_proof = proof[:1] + bytes([0]) + proof[2:]
+        self.assertFalse(merkle_tree.verify_proof(sorted_proof, tree.root, leaves[3], 3, 5))
+        # And for another hash function
+        other_hasher = proof[:2] + bytes([3]) + proof[3:]
+        self.assertFalse(merkle_tree.verify_proof(other_hasher, tree.root, leaves[3], 3, 5))
+
+    def test_defaults_round_trip(self):
+        # Leaves, trees and proofs made with the defaults all agree
+        files = [str(x).encode() for x in range(5)]
+        leaves = [merkle_tree.hash_leaf(file) for file in files]
+        tree = merkle_tree.MerkleTree(leaves)
+        self.assertEqual(tree.scheme, "domain_separated")
+        for index, leaf in enumerate(leaves):
+            proof = tree.prove(index)
+            self.assertTrue(merkle_tree.verify_at_index(tree.root, leaf, index, len(leaves), proof))
+            self.assertTrue(
+                merkle_tree.verify_proof(tree.prove_leaf(index), tree.root, leaf, index, len(leaves))
+            )
+
+    def test_chunked_and_salted_leaves(self):
+        data = bytes(range(250))
+        leaf = merkle_tree.file_leaf(data, 100)
+        self.assertNotEqual(leaf, merkle_tree.hash_leaf(data))
+        self.assertNotEqual(leaf, merkle_tree.file_leaf(data, 50))
+
+        # The leaf is 0x02, the chunk size and file length as little endian u64s, then the root of
+        # the domain separated tree over the chunks
+        chunks = [merkle_tree.hash_leaf(data[x : x + 100]) for x in range(0, 250, 100)]
+        chunk_root = merkle_tree.MerkleTree(chunks).root
+        expected = hashlib.sha256(
+            b"\x02" + (100).to_bytes(8, "little") + (250).to_bytes(8, "little") + chunk_root
+        ).digest()
+        self.assertEqual(leaf, expected)
+
+        # A salted leaf is 0x03, the salt, then the leaf
+        salt = bytes([7] * 32)
+        self.assertEqual(
+            merkle_tree.salt_leaf(salt, leaf), hashlib.sha256(b"\x03" + salt + leaf).digest()
+        )
+        with self.assertRaisesRegex(ValueError, "Chunk size must be greater than 0"):
+            merkle_tree.file_leaf(data, 0)
+        with self.assertRaisesRegex(ValueError, "Salt must be 32 bytes, not 3"):
+            merkle_tree.salt_leaf(b"abc", leaf)
+
+
+if __name__ == "__main__":
+    unittest.main()
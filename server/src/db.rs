@@ -1,13 +1,26 @@
-use crate::storage_server::Database;
+use crate::storage_server::{Database, StorageError};
+
+use merkle_tree::{
+    tree_head::SigningKey, Digest, Salt, Scheme, SignedTreeHead, StoredTreeHeader,
+    STORED_HEADER_LEN,
+};
+use std::path::Path;
 
 use simple_database::SimpleStringDb;
 
 static DB_FILES_FILE_NAME: &str = "files.db";
 static DB_HASHES_FILE_NAME: &str = "hashes.db";
-
+static DB_SCHEME_FILE_NAME: &str = "scheme.db";
+static DB_CHUNK_SIZE_FILE_NAME: &str = "chunk_size.db";
+static DB_SALTS_FILE_NAME: &str = "salts.db";
+static DB_TREE_FILE_NAME: &str = "tree.db";
+static DB_TREE_HEAD_FILE_NAME: &str = "tree_head.db";
+static DB_SIGNING_KEY_FILE_NAME: &str = "server_key.db";
+/// Public key written next to the secret key, to be copied to clients which pin it
+static PUBLIC_KEY_FILE_NAME: &str = "server_key.pub";
 
 impl Database for SimpleStringDb {
-    fn write_files<T: serde::Serialize>(&self, items: &Vec<T>) -> () {
+    fn write_files<T: serde::Serialize>(&self, items: &[T]) {
         let serialised_data = serde_json::to_string(items).unwrap();
         self.write_data_to_file(DB_FILES_FILE_NAME, &serialised_data)
     }
@@ -17,13 +30,115 @@ impl Database for SimpleStringDb {
         serde_json::from_str(&data).unwrap()
     }
 
-    fn write_hashes(&self, items: &Vec<String>) {
+    fn write_hashes(&self, items: &[Digest]) {
         let serialised_data = serde_json::to_string(items).unwrap();
         self.write_data_to_file(DB_HASHES_FILE_NAME, &serialised_data)
     }
 
-    fn read_hashes(&self) -> Vec<String> {
+    fn read_hashes(&self) -> Vec<Digest> {
         let data = self.read_data_from_file(DB_HASHES_FILE_NAME);
         serde_json::from_str(&data).unwrap()
     }
-}
\ No newline at end of file
+
+    fn write_scheme(&self, scheme: Scheme) {
+        let serialised_data = serde_json::to_string(&scheme).unwrap();
+        self.write_data_to_file(DB_SCHEME_FILE_NAME, &serialised_data)
+    }
+
+    // Files stored before schemes existed have no scheme file and were built with the legacy scheme
+    fn read_scheme(&self) -> Scheme {
+        if !Path::new(DB_SCHEME_FILE_NAME).exists() {
+            return Scheme::Sorted;
+        }
+        let data = self.read_data_from_file(DB_SCHEME_FILE_NAME);
+        serde_json::from_str(&data).unwrap()
+    }
+
+    fn write_chunk_size(&self, chunk_size: Option<usize>) {
+        let serialised_data = serde_json::to_string(&chunk_size).unwrap();
+        self.write_data_to_file(DB_CHUNK_SIZE_FILE_NAME, &serialised_data)
+    }
+
+    // Files stored before chunk trees existed have no chunk size file and were hashed whole
+    fn read_chunk_size(&self) -> Option<usize> {
+        if !Path::new(DB_CHUNK_SIZE_FILE_NAME).exists() {
+            return None;
+        }
+        let data = self.read_data_from_file(DB_CHUNK_SIZE_FILE_NAME);
+        serde_json::from_str(&data).unwrap()
+    }
+
+    fn write_salts(&self, salts: Option<&[Salt]>) {
+        let serialised_data = serde_json::to_string(&salts).unwrap();
+        self.write_data_to_file(DB_SALTS_FILE_NAME, &serialised_data)
+    }
+
+    // Files stored before salts existed have no salts file and were not salted
+    fn read_salts(&self) -> Option<Vec<Salt>> {
+        if !Path::new(DB_SALTS_FILE_NAME).exists() {
+            return None;
+        }
+        let data = self.read_data_from_file(DB_SALTS_FILE_NAME);
+        serde_json::from_str(&data).unwrap()
+    }
+
+    fn write_tree(&self, tree: &[u8]) {
+        self.write_bytes_to_file(DB_TREE_FILE_NAME, tree)
+    }
+
+    // The header gives the length of the rest of the tree
+    fn read_tree(&self) -> Option<Vec<u8>> {
+        let header = self.read_bytes_from_file(DB_TREE_FILE_NAME, 0, STORED_HEADER_LEN)?;
+        let stored_len = StoredTreeHeader::from_bytes(&header).ok()?.stored_len();
+        self.read_bytes_from_file(DB_TREE_FILE_NAME, 0, stored_len)
+    }
+
+    fn read_tree_bytes(&self, offset: usize, len: usize) -> Option<Vec<u8>> {
+        self.read_bytes_from_file(DB_TREE_FILE_NAME, offset, len)
+    }
+
+    fn remove_tree(&self) {
+        self.remove_file(DB_TREE_FILE_NAME)
+    }
+
+    // The secret key is stored as hex, readable only by the server's user, with the public key
+    // alongside it for clients to pin
+    fn write_signing_key(&self, signing_key: &SigningKey) {
+        self.write_private_data_to_file(
+            DB_SIGNING_KEY_FILE_NAME,
+            &base16ct::lower::encode_string(signing_key.as_bytes()),
+        );
+        self.write_data_to_file(
+            PUBLIC_KEY_FILE_NAME,
+            &base16ct::lower::encode_string(signing_key.verifying_key().as_bytes()),
+        )
+    }
+
+    fn read_signing_key(&self) -> Result<Option<SigningKey>, StorageError> {
+        if !Path::new(DB_SIGNING_KEY_FILE_NAME).exists() {
+            return Ok(None);
+        }
+        let data = self.read_data_from_file(DB_SIGNING_KEY_FILE_NAME);
+        let mut secret_key = [0u8; 32];
+        match base16ct::mixed::decode(data.trim(), &mut secret_key) {
+            Ok(decoded) if decoded.len() == 32 => Ok(Some(SigningKey::from_bytes(&secret_key))),
+            _ => Err(StorageError::InvalidSigningKey {
+                file_name: DB_SIGNING_KEY_FILE_NAME.to_owned(),
+            }),
+        }
+    }
+
+    fn write_tree_head(&self, signed_tree_head: &SignedTreeHead) {
+        let serialised_data = serde_json::to_string(signed_tree_head).unwrap();
+        self.write_data_to_file(DB_TREE_HEAD_FILE_NAME, &serialised_data)
+    }
+
+    // Files stored before tree heads were signed have no tree head file
+    fn read_tree_head(&self) -> Option<SignedTreeHead> {
+        if !Path::new(DB_TREE_HEAD_FILE_NAME).exists() {
+            return None;
+        }
+        let data = self.read_data_from_file(DB_TREE_HEAD_FILE_NAME);
+        Some(serde_json::from_str(&data).unwrap())
+    }
+}
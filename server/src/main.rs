@@ -2,31 +2,88 @@
 extern crate rocket;
 
 extern crate server;
-use merkle_tree::interface::{FetchRequest, FetchResponse, StoreRequest, StoreResponse};
-use rocket::{serde::json::Json, State};
-use server::storage_server::StorageServer;
+use merkle_tree::interface::{
+    AppendRequest, AppendResponse, FetchRangeRequest, FetchRangeResponse, FetchRequest,
+    FetchResponse, StoreRequest, StoreResponse, UpdateRequest, UpdateResponse,
+};
+use rocket::{response::status::BadRequest, serde::json::Json, State};
+use server::storage_server::{StorageServer, TreeStorage};
 use simple_database::SimpleStringDb;
 
 #[post("/store", format = "application/json", data = "<store_request>")]
 pub fn store(
     server: &State<StorageServer<SimpleStringDb>>,
     store_request: Json<StoreRequest>,
-) -> Json<StoreResponse> {
-    Json(server.add_files(&store_request))
+) -> Result<Json<StoreResponse>, BadRequest<String>> {
+    server
+        .add_files(&store_request)
+        .map(Json)
+        .map_err(|error| BadRequest(error.to_string()))
+}
+
+#[post("/append", format = "application/json", data = "<append_request>")]
+pub fn append(
+    server: &State<StorageServer<SimpleStringDb>>,
+    append_request: Json<AppendRequest>,
+) -> Result<Json<AppendResponse>, BadRequest<String>> {
+    server
+        .append_files(&append_request)
+        .map(Json)
+        .map_err(|error| BadRequest(error.to_string()))
+}
+
+#[post("/update", format = "application/json", data = "<update_request>")]
+pub fn update(
+    server: &State<StorageServer<SimpleStringDb>>,
+    update_request: Json<UpdateRequest>,
+) -> Result<Json<UpdateResponse>, BadRequest<String>> {
+    server
+        .update_file(&update_request)
+        .map(Json)
+        .map_err(|error| BadRequest(error.to_string()))
 }
 
 #[get("/fetch", format = "application/json", data = "<fetch_request>")]
 pub fn fetch(
     server: &State<StorageServer<SimpleStringDb>>,
     fetch_request: Json<FetchRequest>,
-) -> Json<FetchResponse> {
-    Json(server.fetch_file(&fetch_request))
+) -> Result<Json<FetchResponse>, BadRequest<String>> {
+    server
+        .fetch_file(&fetch_request)
+        .map(Json)
+        .map_err(|error| BadRequest(error.to_string()))
+}
+
+#[get(
+    "/fetch-range",
+    format = "application/json",
+    data = "<fetch_range_request>"
+)]
+pub fn fetch_range(
+    server: &State<StorageServer<SimpleStringDb>>,
+    fetch_range_request: Json<FetchRangeRequest>,
+) -> Result<Json<FetchRangeResponse>, BadRequest<String>> {
+    server
+        .fetch_range(&fetch_range_request)
+        .map(Json)
+        .map_err(|error| BadRequest(error.to_string()))
 }
 
 #[launch]
 fn rocket() -> _ {
-    let server = StorageServer::new(SimpleStringDb::new());
-    rocket::build()
-        .mount("/", routes![fetch, store])
+    let rocket = rocket::build();
+    // Set with `tree_storage` in Rocket.toml or the ROCKET_TREE_STORAGE environment variable
+    let tree_storage = match rocket.figment().extract_inner::<String>("tree_storage") {
+        Ok(setting) => setting.parse().unwrap_or_else(|error| panic!("{}", error)),
+        Err(_) => TreeStorage::default(),
+    };
+    let server = StorageServer::with_tree_storage(SimpleStringDb::new(), tree_storage);
+    // Clients pin this key, which is also in server_key.pub, to check signed tree heads
+    println!(
+        "Server public key: {}",
+        base16ct::lower::encode_string(server.public_key().as_bytes())
+    );
+    rocket
+        .mount("/", routes![append, fetch, fetch_range, store, update])
         .manage(server)
 }
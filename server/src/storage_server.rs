@@ -1,57 +1,406 @@
 use merkle_tree::{
-    interface::{FetchRequest, FetchResponse, StoreRequest, StoreResponse},
-    MerkleTree,
+    interface::{
+        AppendRequest, AppendResponse, FetchRangeRequest, FetchRangeResponse, FetchRequest,
+        FetchResponse, StoreRequest, StoreResponse, UpdateRequest, UpdateResponse,
+    },
+    prove_from_stored,
+    tree_head::{SigningKey, VerifyingKey},
+    ChunkTree, Digest, FileRangeProof, MerkleError, MerkleTree, Proof, Salt, Scheme, Sha256,
+    SignedTreeHead,
 };
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// StorageServer provides data storage and retrieval along with a Merkle proof of data integrity  
 /// Requires a Database with basic write/read capability
 pub struct StorageServer<D: Database> {
     pub db: D,
+    pub tree_storage: TreeStorage,
+    /// Key tree heads are signed with, which is generated and stored the first time the server runs
+    signing_key: SigningKey,
 }
 
 impl<D: Database> StorageServer<D> {
     pub fn new(db: D) -> Self {
-        StorageServer { db }
+        StorageServer::with_tree_storage(db, TreeStorage::default())
+    }
+
+    /// Panics if the stored signing key is invalid. See `try_with_tree_storage`.
+    pub fn with_tree_storage(db: D, tree_storage: TreeStorage) -> Self {
+        Self::try_with_tree_storage(db, tree_storage).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// As `with_tree_storage`, but returns an error rather than panicking if the stored signing key
+    /// is invalid
+    pub fn try_with_tree_storage(db: D, tree_storage: TreeStorage) -> Result<Self, StorageError> {
+        let signing_key = match db.read_signing_key()? {
+            Some(signing_key) => signing_key,
+            None => {
+                let mut secret_key = [0u8; 32];
+                getrandom::getrandom(&mut secret_key).unwrap();
+                let signing_key = SigningKey::from_bytes(&secret_key);
+                db.write_signing_key(&signing_key);
+                signing_key
+            }
+        };
+        Ok(StorageServer {
+            db,
+            tree_storage,
+            signing_key,
+        })
+    }
+
+    /// Key clients pin to check the server's signed tree heads
+    pub fn public_key(&self) -> VerifyingKey {
+        self.signing_key.verifying_key()
+    }
+}
+
+/// How the server gets the Merkle tree of the stored files when serving a request. This trades
+/// CPU for storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TreeStorage {
+    /// Rebuild the tree from the stored hashes for every request. Nothing beyond the hashes is stored
+    #[default]
+    Rebuild,
+    /// Store the whole tree whenever the files change, so a fetch reads only the nodes of its proof
+    Persist,
+}
+
+impl FromStr for TreeStorage {
+    type Err = String;
+
+    fn from_str(setting: &str) -> Result<Self, Self::Err> {
+        match setting {
+            "rebuild" => Ok(TreeStorage::Rebuild),
+            "persist" => Ok(TreeStorage::Persist),
+            _ => Err(format!(
+                "Unknown tree storage setting {}. Expected rebuild or persist",
+                setting
+            )),
+        }
     }
 }
 
 /// Database defines a trait for storage of "files" which can be any serialiseable type and "hashes" which are strings
+/// along with the scheme used to build the Merkle tree from the hashes
 pub trait Database {
-    fn write_files<T: serde::Serialize>(&self, items: &Vec<T>) -> ();
+    fn write_files<T: serde::Serialize>(&self, items: &[T]);
     fn read_files<T: for<'a> serde::Deserialize<'a>>(&self) -> Vec<T>;
-    fn write_hashes(&self, items: &Vec<String>) -> ();
-    fn read_hashes(&self) -> Vec<String>;
+    fn write_hashes(&self, items: &[Digest]);
+    fn read_hashes(&self) -> Vec<Digest>;
+    fn write_scheme(&self, scheme: Scheme);
+    fn read_scheme(&self) -> Scheme;
+    /// Chunk size of the `ChunkTree` each hash is the leaf of, or None if files were hashed whole
+    fn write_chunk_size(&self, chunk_size: Option<usize>);
+    fn read_chunk_size(&self) -> Option<usize>;
+    /// Salt of each file, or None if the hashes are not salted
+    fn write_salts(&self, salts: Option<&[Salt]>);
+    fn read_salts(&self) -> Option<Vec<Salt>>;
+    /// Store a tree encoded with `MerkleTree::to_bytes`
+    fn write_tree(&self, tree: &[u8]);
+    /// Load the whole stored tree, if there is one
+    fn read_tree(&self) -> Option<Vec<u8>>;
+    /// Read part of the stored tree without loading the rest of it. None if there is no stored
+    /// tree or it is too short
+    fn read_tree_bytes(&self, offset: usize, len: usize) -> Option<Vec<u8>>;
+    fn remove_tree(&self);
+    fn write_signing_key(&self, signing_key: &SigningKey);
+    /// The server's key, or None if it has not been generated yet. An error if the stored key is
+    /// not a valid key
+    fn read_signing_key(&self) -> Result<Option<SigningKey>, StorageError>;
+    /// Tree head signed when the stored files last changed
+    fn write_tree_head(&self, signed_tree_head: &SignedTreeHead);
+    fn read_tree_head(&self) -> Option<SignedTreeHead>;
+}
+
+/// Reasons a request cannot be served
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    /// A request which does not have one hash for each file
+    FileCountMismatch { files: usize, hashes: usize },
+    /// A range fetch from files which were hashed whole rather than as chunk trees
+    NotChunked,
+    /// Salts which are not one for each file of a salted collection, or which are sent for a
+    /// collection stored without salts
+    SaltMismatch,
+    /// The request cannot be applied to the Merkle tree of the stored files
+    Merkle(MerkleError),
+    /// The file holding the server's signing key does not hold a valid key
+    InvalidSigningKey { file_name: String },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::FileCountMismatch { files, hashes } => write!(
+                f,
+                "Number of files ({}) is not equal to number of hashes ({})",
+                files, hashes
+            ),
+            StorageError::NotChunked => write!(
+                f,
+                "Files were stored without a chunk size, so ranges of them cannot be proven"
+            ),
+            StorageError::SaltMismatch => write!(
+                f,
+                "Salts must be sent, one for each file, if and only if the files are stored salted"
+            ),
+            StorageError::Merkle(error) => error.fmt(f),
+            StorageError::InvalidSigningKey { file_name } => write!(
+                f,
+                "Signing key file {} does not hold a hex encoded Ed25519 secret key. Restore it, or \
+                 remove it to generate a new key",
+                file_name
+            ),
+        }
+    }
+}
+
+impl From<MerkleError> for StorageError {
+    fn from(error: MerkleError) -> Self {
+        StorageError::Merkle(error)
+    }
+}
+
+/// Return an error unless there is one hash for each file
+fn check_file_count(files: &[String], hashes: &[Digest]) -> Result<(), StorageError> {
+    if files.len() != hashes.len() {
+        return Err(StorageError::FileCountMismatch {
+            files: files.len(),
+            hashes: hashes.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Return an error unless there is one salt for each file if the collection is salted, and none
+/// otherwise
+fn check_salt_count(
+    files: &[String],
+    salts: Option<&[Salt]>,
+    salted: bool,
+) -> Result<(), StorageError> {
+    match salts {
+        Some(salts) if salted && salts.len() == files.len() => Ok(()),
+        None if !salted => Ok(()),
+        _ => Err(StorageError::SaltMismatch),
+    }
 }
 
 impl<D: Database> StorageServer<D> {
+    /// Load the persisted tree if there is one and the server persists trees, otherwise rebuild it
+    /// from the stored hashes
+    fn load_tree(&self) -> Result<MerkleTree<Sha256>, StorageError> {
+        if self.tree_storage == TreeStorage::Persist {
+            if let Some(Ok(merkle_tree)) = self
+                .db
+                .read_tree()
+                .map(|bytes| MerkleTree::from_bytes(&bytes))
+            {
+                return Ok(merkle_tree);
+            }
+        }
+        Ok(MerkleTree::try_build_with_scheme(
+            &self.db.read_hashes(),
+            self.db.read_scheme(),
+        )?)
+    }
+
+    /// Persist the tree if the server persists trees. Otherwise remove any tree persisted earlier,
+    /// which would go stale as the files change
+    fn save_tree(&self, merkle_tree: &MerkleTree<Sha256>) {
+        match self.tree_storage {
+            TreeStorage::Persist => self.db.write_tree(&merkle_tree.to_bytes()),
+            TreeStorage::Rebuild => self.db.remove_tree(),
+        }
+    }
+
+    /// Sign a head for the tree of the stored files as it is now and store it, to be returned with
+    /// every fetch until the files change again
+    fn sign_tree_head(&self, merkle_tree: &MerkleTree<Sha256>) -> SignedTreeHead {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let signed_tree_head = merkle_tree.tree_head(timestamp).sign(&self.signing_key);
+        self.db.write_tree_head(&signed_tree_head);
+        signed_tree_head
+    }
+
+    /// Stored tree head. Files stored before tree heads were signed have none, so one is signed now
+    fn read_tree_head(&self) -> Result<SignedTreeHead, StorageError> {
+        match self.db.read_tree_head() {
+            Some(signed_tree_head) => Ok(signed_tree_head),
+            None => Ok(self.sign_tree_head(&self.load_tree()?)),
+        }
+    }
+
     /// Store files and return root of merkle tree they generate
-    pub fn add_files(&self, store_request: &StoreRequest) -> StoreResponse {
-        if store_request.files.len() != store_request.hashes.len() {
-            panic!("Error: Number of files is not equal to number of hashes")
+    pub fn add_files(&self, store_request: &StoreRequest) -> Result<StoreResponse, StorageError> {
+        check_file_count(&store_request.files, &store_request.hashes)?;
+        // A chunk size of 0 would fail every later range fetch
+        if store_request.chunk_size == Some(0) {
+            return Err(MerkleError::InvalidChunkSize.into());
         }
+        let salts = store_request.salts.as_deref();
+        check_salt_count(&store_request.files, salts, salts.is_some())?;
+        let merkle_tree: MerkleTree<Sha256> =
+            MerkleTree::try_build_with_scheme(&store_request.hashes, store_request.scheme)?;
 
         self.db.write_files(&store_request.files);
         self.db.write_hashes(&store_request.hashes);
+        self.db.write_scheme(store_request.scheme);
+        self.db.write_chunk_size(store_request.chunk_size);
+        self.db.write_salts(salts);
+        self.save_tree(&merkle_tree);
 
-        let merkle_tree: MerkleTree = MerkleTree::build(&store_request.hashes);
-
-        StoreResponse {
+        Ok(StoreResponse {
             root: merkle_tree.get_root(),
+            signed_tree_head: self.sign_tree_head(&merkle_tree),
+        })
+    }
+
+    /// Add files to the end of the stored collection and return the new root of the merkle tree along
+    /// with a proof that the files stored before were left unchanged
+    pub fn append_files(
+        &self,
+        append_request: &AppendRequest,
+    ) -> Result<AppendResponse, StorageError> {
+        check_file_count(&append_request.files, &append_request.hashes)?;
+        let mut salts = self.db.read_salts();
+        check_salt_count(
+            &append_request.files,
+            append_request.salts.as_deref(),
+            salts.is_some(),
+        )?;
+
+        let mut files: Vec<String> = self.db.read_files();
+        let mut hashes = self.db.read_hashes();
+        let mut merkle_tree = self.load_tree()?;
+        let root = merkle_tree.extend(&append_request.hashes);
+        let consistency_proof = merkle_tree.try_prove_consistency(hashes.len())?;
+
+        files.extend_from_slice(&append_request.files);
+        hashes.extend_from_slice(&append_request.hashes);
+        if let (Some(salts), Some(new_salts)) = (&mut salts, &append_request.salts) {
+            salts.extend_from_slice(new_salts);
         }
+        self.db.write_files(&files);
+        self.db.write_hashes(&hashes);
+        self.db.write_salts(salts.as_deref());
+        self.save_tree(&merkle_tree);
+
+        Ok(AppendResponse {
+            root,
+            consistency_proof,
+            signed_tree_head: self.sign_tree_head(&merkle_tree),
+        })
+    }
+
+    /// Replace the file at an index and return the new root of the merkle tree along with a proof
+    /// that no other file changed
+    pub fn update_file(
+        &self,
+        update_request: &UpdateRequest,
+    ) -> Result<UpdateResponse, StorageError> {
+        let mut salts = self.db.read_salts();
+        if update_request.salt.is_some() != salts.is_some() {
+            return Err(StorageError::SaltMismatch);
+        }
+        let mut files: Vec<String> = self.db.read_files();
+        let mut hashes = self.db.read_hashes();
+        let mut merkle_tree = self.load_tree()?;
+
+        let index = update_request.file_index;
+        let update = merkle_tree.try_update(index, update_request.hash)?;
+
+        files[index] = update_request.file.clone();
+        hashes[index] = update_request.hash;
+        if let (Some(salts), Some(salt)) = (&mut salts, update_request.salt) {
+            salts[index] = salt;
+        }
+        self.db.write_files(&files);
+        self.db.write_hashes(&hashes);
+        self.db.write_salts(salts.as_deref());
+        self.save_tree(&merkle_tree);
+
+        Ok(UpdateResponse {
+            update,
+            signed_tree_head: self.sign_tree_head(&merkle_tree),
+        })
+    }
+
+    /// Proof for the file at an index in the Merkle tree of all files, read from the persisted tree
+    /// if the server persists trees
+    fn prove_file(&self, index: usize) -> Result<Proof, StorageError> {
+        let proof = match self.tree_storage {
+            TreeStorage::Persist => {
+                match prove_from_stored::<Sha256>(index, |offset, len| {
+                    self.db.read_tree_bytes(offset, len)
+                }) {
+                    // No tree has been persisted yet, as the files were stored while rebuilding
+                    Err(MerkleError::InvalidStoredTree) => {
+                        let merkle_tree = self.load_tree()?;
+                        self.save_tree(&merkle_tree);
+                        merkle_tree.try_prove_leaf(index)?
+                    }
+                    proof => proof?,
+                }
+            }
+            TreeStorage::Rebuild => self.load_tree()?.try_prove_leaf(index)?,
+        };
+        Ok(proof)
+    }
+
+    /// Salt of the file at an index, if the files are stored salted
+    fn read_salt(&self, index: usize) -> Option<Salt> {
+        self.db.read_salts().map(|salts| salts[index])
     }
 
     // Return file of given index along with merkle proof of its existence in Merkle tree built with all files
-    pub fn fetch_file(&self, fetch_request: &FetchRequest) -> FetchResponse {
+    // and the signed head of that tree
+    pub fn fetch_file(&self, fetch_request: &FetchRequest) -> Result<FetchResponse, StorageError> {
         let files: Vec<String> = self.db.read_files();
-        let hashes = self.db.read_hashes();
-        let merkle_tree = MerkleTree::build(&hashes);
 
         let index = fetch_request.file_index;
-        let proof = merkle_tree.prove(index.try_into().unwrap());
+        let proof = self.prove_file(index)?;
 
-        FetchResponse {
+        Ok(FetchResponse {
             file: files[index].clone(),
             proof,
-        }
+            salt: self.read_salt(index),
+            signed_tree_head: self.read_tree_head()?,
+        })
+    }
+
+    /// Return the chunks of the file at an index which hold a byte range, along with proofs that
+    /// they are in the file and that the file is in the Merkle tree built with all files
+    pub fn fetch_range(
+        &self,
+        fetch_range_request: &FetchRangeRequest,
+    ) -> Result<FetchRangeResponse, StorageError> {
+        let chunk_size = self.db.read_chunk_size().ok_or(StorageError::NotChunked)?;
+        let index = fetch_range_request.file_index;
+        // Proving the file first rejects an index past the last file
+        let file_proof = self.prove_file(index)?;
+
+        let files: Vec<String> = self.db.read_files();
+        let file = files[index].as_bytes();
+        let chunk_tree = ChunkTree::<Sha256>::try_build(file, chunk_size)?;
+        let (start, end) = (fetch_range_request.start, fetch_range_request.end);
+        let chunks_proof = chunk_tree.try_prove_range(start, end)?;
+        let (chunks_start, chunks_end) = chunk_tree.chunk_byte_range(start, end)?;
+
+        Ok(FetchRangeResponse {
+            data: file[chunks_start..chunks_end].to_vec(),
+            proof: FileRangeProof {
+                chunks: chunks_proof,
+                file: file_proof,
+                salt: self.read_salt(index),
+            },
+            signed_tree_head: self.read_tree_head()?,
+        })
     }
 }
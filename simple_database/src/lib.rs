@@ -1,8 +1,12 @@
-use std::fs;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 
 /// A simple lcoal filesystem storage mechanism:  
 /// - Stores a single vector of some Serialisable "file" type in local filesystem
 /// - Stores a single vector of strings which are the hashes of the stored "files"
+#[derive(Default)]
 pub struct SimpleStringDb;
 
 impl SimpleStringDb {
@@ -14,22 +18,106 @@ impl SimpleStringDb {
         fs::write(file_name, data).unwrap();
     }
 
+    /// As `write_data_to_file`, but only the owner can read or write the file, for secrets such as
+    /// keys. A file which already exists is made private before it is written
+    pub fn write_private_data_to_file(&self, file_name: &str, data: &str) {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(file_name).unwrap();
+        #[cfg(unix)]
+        file.set_permissions(fs::Permissions::from_mode(0o600)).unwrap();
+        file.write_all(data.as_bytes()).unwrap();
+    }
+
     pub fn read_data_from_file(&self, file_name: &str) -> String {
         String::from_utf8_lossy(&fs::read(file_name).unwrap())
             .parse()
             .unwrap()
     }
+
+    pub fn write_bytes_to_file(&self, file_name: &str, data: &[u8]) {
+        fs::write(file_name, data).unwrap();
+    }
+
+    /// Read `len` bytes starting at `offset` without reading the rest of the file. Returns None if
+    /// the file does not exist or ends before `offset + len`
+    pub fn read_bytes_from_file(
+        &self,
+        file_name: &str,
+        offset: usize,
+        len: usize,
+    ) -> Option<Vec<u8>> {
+        let mut file = match File::open(file_name) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return None,
+            Err(error) => panic!("{}", error),
+        };
+        file.seek(SeekFrom::Start(offset as u64)).unwrap();
+        let mut data = vec![0u8; len];
+        match file.read_exact(&mut data) {
+            Ok(()) => Some(data),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => None,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Delete a file if it exists
+    pub fn remove_file(&self, file_name: &str) {
+        match fs::remove_file(file_name) {
+            Err(error) if error.kind() != ErrorKind::NotFound => panic!("{}", error),
+            _ => (),
+        }
+    }
 }
 
 
+/// Path in the system temp directory for a test's file, so tests leave nothing in the repository
+#[cfg(test)]
+fn temp_file_name(name: &str) -> String {
+    let file_name = format!("simple_database_{}_{}", std::process::id(), name);
+    std::env::temp_dir().join(file_name).to_str().unwrap().to_owned()
+}
+
 #[test]
 fn test_write_read() {
     let db = SimpleStringDb;
+    let file_name = temp_file_name("foo");
     let data = vec![String::from("0"), String::from("1")];
     let data_in = serde_json::to_string(&data).unwrap();
-    db.write_data_to_file("foo", &data_in);
-    let data_out = db.read_data_from_file("foo");
+    db.write_data_to_file(&file_name, &data_in);
+    let data_out = db.read_data_from_file(&file_name);
+    db.remove_file(&file_name);
     let data_out_deserialised: Vec<String> = serde_json::from_str(&data_out).unwrap();
     assert_eq!(data_in, data_out);
     assert_eq!(data, data_out_deserialised);
 }
+
+#[test]
+fn test_read_bytes_at_offset() {
+    let db = SimpleStringDb;
+    let file_name = temp_file_name("bar");
+    db.write_bytes_to_file(&file_name, &[0, 1, 2, 3, 4, 5]);
+    assert_eq!(db.read_bytes_from_file(&file_name, 2, 3), Some(vec![2, 3, 4]));
+    assert_eq!(db.read_bytes_from_file(&file_name, 4, 3), None);
+    db.remove_file(&file_name);
+    assert_eq!(db.read_bytes_from_file(&file_name, 0, 1), None);
+    db.remove_file(&file_name);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_private_file_is_owner_only() {
+    let db = SimpleStringDb;
+    let file_name = temp_file_name("private");
+    // An existing file readable by others is made private too
+    db.write_data_to_file(&file_name, "public");
+    fs::set_permissions(&file_name, fs::Permissions::from_mode(0o644)).unwrap();
+    db.write_private_data_to_file(&file_name, "secret");
+    let mode = fs::metadata(&file_name).unwrap().permissions().mode();
+    let data_out = db.read_data_from_file(&file_name);
+    db.remove_file(&file_name);
+    assert_eq!(mode & 0o777, 0o600);
+    assert_eq!(data_out, "secret");
+}